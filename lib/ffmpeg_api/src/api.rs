@@ -1,14 +1,18 @@
+use std::io::{Read, Seek, SeekFrom};
 use std::marker::PhantomData;
+use std::os::raw::{c_int, c_void};
 use std::path::{Path, PathBuf};
 
 use ffmpeg_dev::sys as ffi;
 use fraction::Fraction;
 use num_traits::FromPrimitive;
-use media_time::MediaTimeError;
+use media_time::{MediaTimeError, Rounding};
 use thiserror::Error;
 
 use crate::enums::*;
 use crate::err::AVError;
+use crate::err_av::AvInternalError;
+use crate::err_ffi::AvFfiError;
 
 #[derive(Error, Debug)]
 pub enum AVAllocError {
@@ -35,8 +39,151 @@ fn native_string(ptr: *const std::os::raw::c_char) -> Result<String, StringError
     }
 }
 
+/// AV_TIME_BASE_Q, the time base of timestamps not belonging to a stream
+fn av_time_base() -> Fraction {
+    Fraction::new(1 as u64, ffi::AV_TIME_BASE as u64)
+}
+
+fn native_string_lossy(ptr: *const std::os::raw::c_char) -> String {
+    unsafe { std::ffi::CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub struct AVDictionary {
+    base: *mut ffi::AVDictionary,
+}
+
+#[derive(Error, Debug)]
+pub enum AVDictionaryError {
+    #[error("Key {0} contains null byte")]
+    KeyContainsNull(String, #[source] std::ffi::NulError),
+    #[error("Value of key {0} contains null byte")]
+    ValueContainsNull(String, #[source] std::ffi::NulError),
+    #[error("Setting key {0} failed")]
+    SetFailed(String, #[source] AVError),
+}
+
+impl AVDictionary {
+    pub fn new() -> Self {
+        // FFmpeg represents an empty dictionary as null, entries are allocated on demand
+        AVDictionary {
+            base: std::ptr::null_mut(),
+        }
+    }
+
+    fn copy_from(base: *const ffi::AVDictionary) -> Self {
+        let mut dictionary = AVDictionary::new();
+        if !base.is_null() {
+            unsafe { ffi::av_dict_copy(&mut dictionary.base, base, 0) };
+        }
+        dictionary
+    }
+
+    fn as_mut_ptr(options: Option<&mut AVDictionary>) -> *mut *mut ffi::AVDictionary {
+        options.map_or(std::ptr::null_mut(), |options| &mut options.base)
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { ffi::av_dict_count(self.base) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks up the value of a key, ignoring the case of the key
+    pub fn get(&self, key: impl AsRef<str>) -> Option<String> {
+        let key = std::ffi::CString::new(key.as_ref()).ok()?;
+        let entry = unsafe { ffi::av_dict_get(self.base, key.as_ptr(), std::ptr::null(), 0).as_ref() }?;
+        Some(native_string_lossy(entry.value))
+    }
+
+    pub fn set(
+        &mut self,
+        key: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<(), AVDictionaryError> {
+        let key = key.as_ref();
+        let native_key = std::ffi::CString::new(key)
+            .map_err(|err| AVDictionaryError::KeyContainsNull(key.to_string(), err))?;
+        let native_value = std::ffi::CString::new(value.as_ref())
+            .map_err(|err| AVDictionaryError::ValueContainsNull(key.to_string(), err))?;
+
+        AVError::from_errno(unsafe {
+            ffi::av_dict_set(&mut self.base, native_key.as_ptr(), native_value.as_ptr(), 0)
+        }).map_err(|err| AVDictionaryError::SetFailed(key.to_string(), err))
+    }
+
+    pub fn iter(&self) -> AVDictionaryIter {
+        AVDictionaryIter {
+            dictionary: self,
+            entry: std::ptr::null(),
+        }
+    }
+}
+
+impl Default for AVDictionary {
+    fn default() -> Self {
+        AVDictionary::new()
+    }
+}
+
+impl Clone for AVDictionary {
+    fn clone(&self) -> Self {
+        AVDictionary::copy_from(self.base)
+    }
+}
+
+impl std::fmt::Debug for AVDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Drop for AVDictionary {
+    fn drop(&mut self) {
+        unsafe { ffi::av_dict_free(&mut self.base) }
+    }
+}
+
+impl<'a> IntoIterator for &'a AVDictionary {
+    type Item = (String, String);
+    type IntoIter = AVDictionaryIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct AVDictionaryIter<'a> {
+    dictionary: &'a AVDictionary,
+    entry: *const ffi::AVDictionaryEntry,
+}
+
+impl<'a> Iterator for AVDictionaryIter<'a> {
+    type Item = (String, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = unsafe {
+            ffi::av_dict_get(
+                self.dictionary.base,
+                b"\0".as_ptr() as *const std::os::raw::c_char,
+                self.entry,
+                ffi::AV_DICT_IGNORE_SUFFIX as c_int,
+            )
+        };
+        self.entry = entry;
+
+        unsafe { entry.as_ref() }
+            .map(|entry| (native_string_lossy(entry.key), native_string_lossy(entry.value)))
+    }
+}
+
 pub struct AVFormatContext {
     base: *mut ffi::AVFormatContext,
+    io: Option<AVIOContext>,
+    interrupt: Option<Box<Box<dyn Fn() -> bool>>>,
 }
 
 #[derive(Error, Debug)]
@@ -48,7 +195,19 @@ pub enum AVFormatContextError {
     #[error("Path {0} contains null byte")]
     PathContainsNull(PathBuf, #[source] std::ffi::NulError),
     #[error("Opening media file {0} failed")]
-    OpenInputFailed(PathBuf, #[source] AVError)
+    OpenInputFailed(PathBuf, #[source] AVError),
+    #[error("Opening media from reader failed")]
+    OpenReaderFailed(#[source] AVError),
+    #[error("Seeking to timestamp {0} failed")]
+    SeekFailed(i64, #[source] AVError),
+    #[error("Reading stream information failed")]
+    FindStreamInfoFailed(#[source] AVError),
+    #[error("Finding a {0:?} stream failed")]
+    FindBestStreamFailed(AVMediaType, #[source] AVError),
+    #[error("Stream #{0} does not exist")]
+    StreamNotFound(i32),
+    #[error("Timestamp is out of range")]
+    TimestampOutOfRange(#[from] MediaTimeError),
 }
 
 impl AVFormatContext {
@@ -57,11 +216,20 @@ impl AVFormatContext {
         return if base.is_null() {
             Err(AVAllocError::AllocFailed("AVFormatContext".to_string()))
         } else {
-            Ok(AVFormatContext { base })
+            Ok(AVFormatContext {
+                base,
+                io: None,
+                interrupt: None,
+            })
         }
     }
 
-    pub fn open_input(&mut self, path: &Path) -> Result<(), AVFormatContextError> {
+    /// Opens a media file, options not consumed by the demuxer are left in `options`
+    pub fn open_input(
+        &mut self,
+        path: &Path,
+        options: Option<&mut AVDictionary>,
+    ) -> Result<(), AVFormatContextError> {
         let pathname = path
             .to_str()
             .ok_or(AVFormatContextError::PathInvalid(path.to_path_buf()))?;
@@ -73,11 +241,46 @@ impl AVFormatContext {
                 &mut self.base,
                 pathname.as_ptr(),
                 std::ptr::null_mut(),
-                std::ptr::null_mut(),
+                AVDictionary::as_mut_ptr(options),
             )
         }).map_err(|err| AVFormatContextError::OpenInputFailed(path.to_path_buf(), err))
     }
 
+    pub fn open_reader(
+        &mut self,
+        reader: impl Read + Seek + 'static,
+        options: Option<&mut AVDictionary>,
+    ) -> Result<(), AVFormatContextError> {
+        let io = AVIOContext::new(reader)?;
+        unsafe {
+            (*self.base).pb = io.base;
+            (*self.base).flags |= ffi::AVFMT_FLAG_CUSTOM_IO as c_int;
+        }
+        self.io = Some(io);
+
+        AVError::from_errno(unsafe {
+            ffi::avformat_open_input(
+                &mut self.base,
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                AVDictionary::as_mut_ptr(options),
+            )
+        }).map_err(|err| AVFormatContextError::OpenReaderFailed(err))
+    }
+
+    /// Installs a callback polled by blocking I/O, returning `true` aborts the operation with
+    /// `AVERROR_EXIT`. Has to be set before opening the input to cover probing as well.
+    pub fn set_interrupt_callback(&mut self, callback: impl Fn() -> bool + 'static) {
+        let mut callback: Box<Box<dyn Fn() -> bool>> = Box::new(Box::new(callback));
+        unsafe {
+            (*self.base).interrupt_callback = ffi::AVIOInterruptCB {
+                callback: Some(interrupt_callback),
+                opaque: callback.as_mut() as *mut Box<dyn Fn() -> bool> as *mut c_void,
+            };
+        }
+        self.interrupt = Some(callback);
+    }
+
     pub fn input_format(&self) -> Result<AVInputFormat, AVInputFormatError> {
         let base: &mut ffi::AVInputFormat = unsafe { (*self.base).iformat.as_mut() }
             .ok_or(AVInputFormatError::Invalid)?;
@@ -94,16 +297,123 @@ impl AVFormatContext {
         .map(|stream| AVStream::new(stream))
     }
 
+    pub fn stream(&self, index: i32) -> Option<AVStream> {
+        if index < 0 || index as u32 >= unsafe { (*self.base).nb_streams } {
+            return None;
+        }
+
+        unsafe { (*(*self.base).streams.offset(index as isize)).as_mut() }
+            .map(|stream| AVStream::new(stream))
+    }
+
+    /// Probes packets to fill in codec parameters that are not available from the header alone
+    pub fn find_stream_info(&mut self) -> Result<(), AVFormatContextError> {
+        AVError::from_errno(unsafe {
+            ffi::avformat_find_stream_info(self.base, std::ptr::null_mut())
+        }).map_err(|err| AVFormatContextError::FindStreamInfoFailed(err))
+    }
+
+    /// Picks the most suitable stream of the given type, preferring `wanted` if it qualifies
+    pub fn find_best_stream(
+        &self,
+        media_type: AVMediaType,
+        wanted: Option<i32>,
+    ) -> Result<AVStream, AVFormatContextError> {
+        let index = unsafe {
+            ffi::av_find_best_stream(
+                self.base,
+                media_type as ffi::AVMediaType,
+                wanted.unwrap_or(-1),
+                -1,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if index < 0 {
+            return Err(AVFormatContextError::FindBestStreamFailed(
+                media_type,
+                AVError::from(-index),
+            ));
+        }
+
+        self.stream(index)
+            .ok_or(AVFormatContextError::StreamNotFound(index))
+    }
+
+    pub fn chapters(&self) -> impl Iterator<Item = AVChapter> {
+        unsafe {
+            std::slice::from_raw_parts((*self.base).chapters, (*self.base).nb_chapters as usize)
+        }
+        .iter()
+        .filter_map(|chapter: &*mut ffi::AVChapter| unsafe { (*chapter).as_ref() })
+        .map(|chapter| AVChapter::new(chapter))
+    }
+
+    pub fn metadata(&self) -> AVDictionary {
+        AVDictionary::copy_from(unsafe { (*self.base).metadata })
+    }
+
     pub fn read_frame(&mut self, packet: &mut AVPacket) -> Result<(), AVFrameError> {
         AVError::from_errno(unsafe { ffi::av_read_frame(self.base, packet.base) })
-            .map_err(|err| AVFrameError::DecodingFailed(packet.stream_index(), packet.pts(), err))
+            .map_err(|err| AVFrameError::DecodingFailed(packet.stream_index(), err))
     }
 
-    pub fn duration(&self) -> Result<media_time::MediaTime, MediaTimeError> {
-        media_time::MediaTime::from_rational(
-            unsafe { (*self.base).duration },
-            &Fraction::new(1 as u64, ffi::AV_TIME_BASE as u64),
-        )
+    pub fn seek_frame(
+        &mut self,
+        stream_index: i32,
+        timestamp: i64,
+        flags: AVSeekFlags,
+    ) -> Result<(), AVFormatContextError> {
+        AVError::from_errno(unsafe {
+            ffi::av_seek_frame(self.base, stream_index, timestamp, flags.bits() as i32)
+        }).map_err(|err| AVFormatContextError::SeekFailed(timestamp, err))
+    }
+
+    pub fn seek_file(
+        &mut self,
+        stream_index: i32,
+        min_timestamp: i64,
+        timestamp: i64,
+        max_timestamp: i64,
+        flags: AVSeekFlags,
+    ) -> Result<(), AVFormatContextError> {
+        AVError::from_errno(unsafe {
+            ffi::avformat_seek_file(
+                self.base,
+                stream_index,
+                min_timestamp,
+                timestamp,
+                max_timestamp,
+                flags.bits() as i32,
+            )
+        }).map_err(|err| AVFormatContextError::SeekFailed(timestamp, err))
+    }
+
+    /// Seeks to the last keyframe at or before a time relative to the start time of the file
+    pub fn seek(&mut self, time: media_time::MediaTime) -> Result<(), AVFormatContextError> {
+        // Without a stream index, timestamps are given in AV_TIME_BASE
+        let mut target = time.to_rational(&av_time_base(), Rounding::Down)?;
+        if let Some(start_time) = timestamp(unsafe { (*self.base).start_time }) {
+            target = target.checked_add(start_time).ok_or(MediaTimeError::Overflow)?;
+        }
+        self.seek_file(-1, i64::MIN, target, target, AVSeekFlags::empty())
+    }
+
+    /// Presentation time of the first frame, which is not zero for e.g. MPEG-TS
+    pub fn start_time(&self) -> Result<Option<media_time::MediaTime>, MediaTimeError> {
+        timestamp(unsafe { (*self.base).start_time })
+            .map(|start_time| media_time::MediaTime::from_rational(start_time, &av_time_base()))
+            .transpose()
+    }
+
+    pub fn bit_rate(&self) -> i64 {
+        unsafe { (*self.base).bit_rate }
+    }
+
+    /// `None` if the container doesn't know its duration
+    pub fn duration(&self) -> Option<media_time::MediaTime> {
+        timestamp(unsafe { (*self.base).duration })
+            .and_then(|duration| media_time::MediaTime::from_rational(duration, &av_time_base()).ok())
     }
 }
 
@@ -113,6 +423,122 @@ impl Drop for AVFormatContext {
     }
 }
 
+unsafe extern "C" fn interrupt_callback(opaque: *mut c_void) -> c_int {
+    let callback = &*(opaque as *const Box<dyn Fn() -> bool>);
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback())) {
+        Ok(false) => 0,
+        _ => 1,
+    }
+}
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+const AVIO_BUFFER_SIZE: usize = 32 * 1024;
+
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
+pub struct AVIOContext {
+    base: *mut ffi::AVIOContext,
+    // Double boxed, as the opaque pointer handed to FFmpeg has to be thin
+    _reader: Box<Box<dyn ReadSeek>>,
+}
+
+impl AVIOContext {
+    pub fn new(reader: impl Read + Seek + 'static) -> Result<Self, AVAllocError> {
+        let mut reader: Box<Box<dyn ReadSeek>> = Box::new(Box::new(reader));
+
+        let buffer = unsafe { ffi::av_malloc(AVIO_BUFFER_SIZE) } as *mut u8;
+        if buffer.is_null() {
+            return Err(AVAllocError::AllocFailed("AVIOContext buffer".to_string()));
+        }
+
+        let base = unsafe {
+            ffi::avio_alloc_context(
+                buffer,
+                AVIO_BUFFER_SIZE as c_int,
+                0,
+                reader.as_mut() as *mut Box<dyn ReadSeek> as *mut c_void,
+                Some(avio_read_packet),
+                None,
+                Some(avio_seek),
+            )
+        };
+        if base.is_null() {
+            unsafe { ffi::av_free(buffer as *mut c_void) };
+            Err(AVAllocError::AllocFailed("AVIOContext".to_string()))
+        } else {
+            Ok(AVIOContext { base, _reader: reader })
+        }
+    }
+}
+
+impl Drop for AVIOContext {
+    fn drop(&mut self) {
+        unsafe {
+            // FFmpeg may have replaced the buffer we allocated, so free whatever it holds now
+            ffi::av_freep(&mut (*self.base).buffer as *mut *mut u8 as *mut c_void);
+            ffi::avio_context_free(&mut self.base);
+        }
+    }
+}
+
+fn averror_io() -> c_int {
+    -(AvFfiError::IoError as c_int)
+}
+
+unsafe extern "C" fn avio_read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let buf = std::slice::from_raw_parts_mut(buf, buf_size as usize);
+
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| loop {
+        match reader.read(buf) {
+            Ok(0) => return -(AvInternalError::EndOfFile as c_int),
+            Ok(read) => return read as c_int,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return averror_io(),
+        }
+    }))
+    .unwrap_or_else(|_| averror_io())
+}
+
+unsafe extern "C" fn avio_seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let whence = whence & !(ffi::AVSEEK_FORCE as c_int);
+
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let result = if whence & ffi::AVSEEK_SIZE as c_int != 0 {
+            stream_len(reader)
+        } else {
+            let position = match whence {
+                SEEK_SET => SeekFrom::Start(offset as u64),
+                SEEK_CUR => SeekFrom::Current(offset),
+                SEEK_END => SeekFrom::End(offset),
+                _ => return -(AvFfiError::InvalidArgument as i64),
+            };
+            reader.seek(position)
+        };
+
+        match result {
+            Ok(position) => position as i64,
+            Err(_) => averror_io() as i64,
+        }
+    }))
+    .unwrap_or_else(|_| averror_io() as i64)
+}
+
+fn stream_len(reader: &mut Box<dyn ReadSeek>) -> std::io::Result<u64> {
+    let current = reader.seek(SeekFrom::Current(0))?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    if current != end {
+        reader.seek(SeekFrom::Start(current))?;
+    }
+    Ok(end)
+}
+
 pub struct AVInputFormat<'a> {
     base: &'a mut ffi::AVInputFormat,
 }
@@ -151,6 +577,10 @@ impl<'a> AVInputFormat<'a> {
                 ("mp4", "h264") | ("mp4", "hevc") => return Ok("video/mp4"),
                 ("matroska", "h264") | ("matroska", "hevc") => return Ok("video/x-matroska"),
                 ("webm", "vp8") | ("webm", "vp9") | ("webm", "av1") => return Ok("video/webm"),
+                ("mp3", "mp3") => return Ok("audio/mpeg"),
+                ("mp4", "aac") | ("mp4", "alac") | ("m4a", "aac") | ("m4a", "alac") => return Ok("audio/mp4"),
+                ("flac", "flac") => return Ok("audio/flac"),
+                ("ogg", "vorbis") | ("ogg", "opus") | ("ogg", "flac") => return Ok("audio/ogg"),
                 _ => {}
             }
         }
@@ -190,6 +620,13 @@ impl AVBuffer {
     }
 }
 
+// AV_NOPTS_VALUE, a macro bindgen can't translate
+const AV_NOPTS_VALUE: i64 = i64::MIN;
+
+fn timestamp(value: i64) -> Option<i64> {
+    if value == AV_NOPTS_VALUE { None } else { Some(value) }
+}
+
 pub struct AVPacket {
     base: *mut ffi::AVPacket,
 }
@@ -214,12 +651,16 @@ impl AVPacket {
         unsafe { self.base.as_ref() }.unwrap_or_else(|| panic!("AVPacket base unexpectedly null"))
     }
 
-    pub fn pts(&self) -> i64 {
-        self.as_ref().pts
+    pub fn pts(&self) -> Option<i64> {
+        timestamp(self.as_ref().pts)
+    }
+
+    pub fn dts(&self) -> Option<i64> {
+        timestamp(self.as_ref().dts)
     }
 
-    pub fn dts(&self) -> i64 {
-        self.as_ref().dts
+    pub fn duration(&self) -> i64 {
+        self.as_ref().duration
     }
 
     pub fn stream_index(&self) -> i32 {
@@ -242,8 +683,8 @@ pub struct AVFrame {
 pub enum AVFrameError {
     #[error(transparent)]
     AllocFailed(#[from] AVAllocError),
-    #[error("Decoding a frame from packet of stream {0} at timestamp {1} failed")]
-    DecodingFailed(i32, i64, #[source] AVError)
+    #[error("Decoding a frame from packet of stream {0} failed")]
+    DecodingFailed(i32, #[source] AVError)
 }
 
 impl AVFrame {
@@ -306,12 +747,95 @@ impl AVFrame {
         }
     }
 
+    pub fn nb_samples(&self) -> i32 {
+        self.as_ref().nb_samples
+    }
+
+    pub fn sample_format(&self) -> AVSampleFormat {
+        AVSampleFormat::from_i32(self.as_ref().format).unwrap_or(AVSampleFormat::NONE)
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.as_ref().sample_rate
+    }
+
+    pub fn channels(&self) -> i32 {
+        self.as_ref().channels
+    }
+
+    pub fn channel_layout(&self) -> u64 {
+        self.as_ref().channel_layout
+    }
+
+    /// The signalled channel layout, or the default one for the channel count if unset
+    pub fn layout(&self) -> AVChannelLayout {
+        match AVChannelLayout::from_bits_truncate(self.channel_layout()) {
+            layout if layout.is_empty() => AVChannelLayout::default_for(self.channels()),
+            layout => layout,
+        }
+    }
+
+    pub fn is_planar(&self) -> bool {
+        unsafe { ffi::av_sample_fmt_is_planar(self.as_ref().format) != 0 }
+    }
+
+    /// Audio samples of one channel for planar formats, or of all channels for packed formats
+    pub fn channel_data(&self, index: usize) -> &[u8] {
+        let planes = if self.is_planar() { self.channels().max(0) as usize } else { 1 };
+        if index >= planes || self.as_ref().extended_data.is_null() {
+            return &[];
+        }
+
+        let bytes_per_sample = unsafe { ffi::av_get_bytes_per_sample(self.as_ref().format) };
+        let samples_per_plane = if self.is_planar() {
+            self.nb_samples()
+        } else {
+            self.nb_samples() * self.channels()
+        };
+        let size = (bytes_per_sample * samples_per_plane).max(0) as usize;
+
+        unsafe {
+            let plane = *self.as_ref().extended_data.offset(index as isize);
+            if plane.is_null() {
+                return &[];
+            }
+            std::slice::from_raw_parts(plane, size)
+        }
+    }
+
+    pub fn extended_data_ptr(&self) -> *const *const u8 {
+        self.as_ref().extended_data as *const *const u8
+    }
+
+    pub fn side_data(&self) -> impl Iterator<Item = AVFrameSideData> {
+        let side_data = if self.as_ref().side_data.is_null() {
+            &[]
+        } else {
+            unsafe {
+                std::slice::from_raw_parts(self.as_ref().side_data, self.as_ref().nb_side_data.max(0) as usize)
+            }
+        };
+        side_data
+            .iter()
+            .filter_map(|side_data| unsafe { side_data.as_ref() })
+            .map(|side_data| AVFrameSideData::new(side_data))
+    }
+
+    pub fn find_side_data(&self, data_type: AVFrameSideDataType) -> Option<AVFrameSideData> {
+        self.side_data().find(|side_data| side_data.data_type() == Some(data_type))
+    }
+
     pub fn key_frame(&self) -> bool {
         self.as_ref().key_frame != 0
     }
 
-    pub fn pts(&self) -> i64 {
-        self.as_ref().pts
+    pub fn pts(&self) -> Option<i64> {
+        timestamp(self.as_ref().pts)
+    }
+
+    /// Timestamp guessed by the decoder from pts and dts, for frames without pts
+    pub fn best_effort_timestamp(&self) -> Option<i64> {
+        timestamp(self.as_ref().best_effort_timestamp)
     }
 
     pub fn coded_picture_number(&self) -> i32 {
@@ -349,6 +873,27 @@ impl Drop for AVFrame {
     }
 }
 
+pub struct AVFrameSideData<'a> {
+    base: &'a ffi::AVFrameSideData,
+}
+
+impl<'a> AVFrameSideData<'a> {
+    fn new(base: &'a ffi::AVFrameSideData) -> Self {
+        return AVFrameSideData { base };
+    }
+
+    pub fn data_type(&self) -> Option<AVFrameSideDataType> {
+        AVFrameSideDataType::from_u32(self.base.type_ as u32)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        if self.base.data.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.base.data, self.base.size.max(0) as usize) }
+    }
+}
+
 pub struct AVStream<'a> {
     base: &'a mut ffi::AVStream,
 }
@@ -373,14 +918,51 @@ impl<'a> AVStream<'a> {
         media_time::MediaTime::from_rational(timestamp, &self.time_base())
     }
 
-    pub fn duration(&self) -> Result<media_time::MediaTime, MediaTimeError> {
-        self.timestamp(self.base.duration)
+    /// Converts a time to a timestamp in the stream's time base
+    pub fn to_timestamp(&self, time: media_time::MediaTime, rounding: Rounding) -> Result<i64, MediaTimeError> {
+        time.to_rational(&self.time_base(), rounding)
+    }
+
+    /// `None` if the stream doesn't know its duration
+    pub fn duration(&self) -> Option<media_time::MediaTime> {
+        timestamp(self.base.duration).and_then(|duration| self.timestamp(duration).ok())
     }
 
     pub fn frame_count(&self) -> i64 {
         self.base.nb_frames
     }
 
+    pub fn avg_frame_rate(&self) -> Fraction {
+        Fraction::new(
+            self.base.avg_frame_rate.num as u32,
+            self.base.avg_frame_rate.den as u32,
+        )
+    }
+
+    pub fn real_frame_rate(&self) -> Fraction {
+        Fraction::new(
+            self.base.r_frame_rate.num as u32,
+            self.base.r_frame_rate.den as u32,
+        )
+    }
+
+    pub fn disposition(&self) -> AVDisposition {
+        AVDisposition::from_bits_truncate(self.base.disposition as u32)
+    }
+
+    /// Encoded cover art, only present for streams with `AVDisposition::ATTACHED_PIC`
+    pub fn attached_pic(&self) -> Option<&[u8]> {
+        let packet = &self.base.attached_pic;
+        if !self.disposition().contains(AVDisposition::ATTACHED_PIC)
+            || packet.data.is_null()
+            || packet.size <= 0
+        {
+            return None;
+        }
+
+        Some(unsafe { std::slice::from_raw_parts(packet.data, packet.size as usize) })
+    }
+
     pub fn discard(&self) -> Option<AVDiscard> {
         AVDiscard::from_i32(self.base.discard)
     }
@@ -403,6 +985,10 @@ impl<'a> AVStream<'a> {
         )
     }
 
+    pub fn metadata(&self) -> AVDictionary {
+        AVDictionary::copy_from(self.base.metadata)
+    }
+
     pub fn codec_parameters(&self) -> Result<AVCodecParameters, AVCodecParametersError> {
         Ok(AVCodecParameters::new(
             unsafe { self.base.codecpar.as_mut() }
@@ -412,6 +998,51 @@ impl<'a> AVStream<'a> {
     }
 }
 
+pub struct AVChapter<'a> {
+    base: &'a ffi::AVChapter,
+}
+
+impl<'a> AVChapter<'a> {
+    fn new(base: &'a ffi::AVChapter) -> Self {
+        return AVChapter { base };
+    }
+
+    pub fn id(&self) -> i32 {
+        self.base.id
+    }
+
+    pub fn time_base(&self) -> Fraction {
+        Fraction::new(
+            self.base.time_base.num as u32,
+            self.base.time_base.den as u32,
+        )
+    }
+
+    pub fn start(&self) -> i64 {
+        self.base.start
+    }
+
+    pub fn end(&self) -> i64 {
+        self.base.end
+    }
+
+    pub fn start_time(&self) -> Result<media_time::MediaTime, MediaTimeError> {
+        media_time::MediaTime::from_rational(self.start(), &self.time_base())
+    }
+
+    pub fn end_time(&self) -> Result<media_time::MediaTime, MediaTimeError> {
+        media_time::MediaTime::from_rational(self.end(), &self.time_base())
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.metadata().get("title")
+    }
+
+    pub fn metadata(&self) -> AVDictionary {
+        AVDictionary::copy_from(self.base.metadata)
+    }
+}
+
 pub struct AVCodecParameters<'a> {
     base: &'a mut ffi::AVCodecParameters,
     phantom: PhantomData<&'a AVStream<'a>>,
@@ -421,6 +1052,8 @@ pub struct AVCodecParameters<'a> {
 pub enum AVCodecParametersError {
     #[error("AVCodecParameters not valid for stream {0}")]
     Invalid(i32),
+    #[error("Field {0} is invalid")]
+    FieldInaccessible(String, #[source] StringError),
 }
 
 impl<'a> AVCodecParameters<'a> {
@@ -439,10 +1072,76 @@ impl<'a> AVCodecParameters<'a> {
         AVCodecID::from_u32(self.base.codec_id)
     }
 
+    pub fn codec_name(&self) -> Result<String, AVCodecParametersError> {
+        native_string(unsafe { ffi::avcodec_get_name(self.base.codec_id) })
+            .map_err(|err| AVCodecParametersError::FieldInaccessible("codec_name".to_string(), err))
+    }
+
     pub fn bit_rate(&self) -> i64 {
         self.base.bit_rate
     }
 
+    pub fn profile(&self) -> i32 {
+        self.base.profile
+    }
+
+    pub fn profile_name(&self) -> Option<String> {
+        native_string(unsafe { ffi::avcodec_profile_name(self.base.codec_id, self.base.profile) })
+            .ok()
+    }
+
+    pub fn level(&self) -> i32 {
+        self.base.level
+    }
+
+    pub fn width(&self) -> i32 {
+        self.base.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.base.height
+    }
+
+    pub fn sample_aspect_ratio(&self) -> Fraction {
+        Fraction::new(
+            self.base.sample_aspect_ratio.num as u32,
+            self.base.sample_aspect_ratio.den as u32,
+        )
+    }
+
+    pub fn pixel_format(&self) -> Option<AVPixelFormat> {
+        match self.codec_type() {
+            AVMediaType::Video => AVPixelFormat::from_i32(self.base.format),
+            _ => None,
+        }
+    }
+
+    /// Name of the pixel format for video streams or the sample format for audio streams
+    pub fn format_name(&self) -> Option<String> {
+        let name = match self.codec_type() {
+            AVMediaType::Video => unsafe { ffi::av_get_pix_fmt_name(self.base.format) },
+            AVMediaType::Audio => unsafe { ffi::av_get_sample_fmt_name(self.base.format) },
+            _ => std::ptr::null(),
+        };
+        native_string(name).ok()
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.base.sample_rate
+    }
+
+    pub fn channels(&self) -> i32 {
+        self.base.channels
+    }
+
+    /// Whether the codec is a subtitle codec with text based events, as opposed to bitmaps
+    pub fn is_text_subtitle(&self) -> bool {
+        match unsafe { ffi::avcodec_descriptor_get(self.base.codec_id).as_ref() } {
+            Some(descriptor) => descriptor.props & ffi::AV_CODEC_PROP_TEXT_SUB as i32 != 0,
+            None => false,
+        }
+    }
+
     pub fn find_decoder(&self) -> Result<AVCodec, AVCodecError> {
         Ok(AVCodec::new(
             unsafe { ffi::avcodec_find_decoder(self.base.codec_id).as_mut() }
@@ -493,6 +1192,10 @@ pub enum AVCodecContextError {
     PacketError(#[source] AVError),
     #[error("Error decoding frame")]
     FrameError(#[source] AVError),
+    #[error("Error opening codec")]
+    OpenFailed(#[source] AVError),
+    #[error("Error decoding subtitle")]
+    SubtitleError(#[source] AVError),
 }
 
 impl AVCodecContext {
@@ -549,17 +1252,54 @@ impl AVCodecContext {
         self.as_mut().skip_frame = value as ffi::AVDiscard
     }
 
+    /// Time base of the packets, required by subtitle decoders to compute display times
+    pub fn set_pkt_timebase(&mut self, time_base: &Fraction) {
+        self.as_mut().pkt_timebase = ffi::AVRational {
+            num: *time_base.numer().unwrap_or(&0) as i32,
+            den: *time_base.denom().unwrap_or(&1) as i32,
+        };
+    }
+
+    /// Decodes a subtitle packet, returns false if the packet contained no subtitle
+    pub fn decode_subtitle(
+        &mut self,
+        packet: &mut AVPacket,
+        subtitle: &mut AVSubtitle,
+    ) -> Result<bool, AVCodecContextError> {
+        // The decoder overwrites the previous subtitle without releasing its rects
+        subtitle.clear();
+        let mut got_subtitle = 0;
+        let value = unsafe {
+            ffi::avcodec_decode_subtitle2(self.base, &mut subtitle.base, &mut got_subtitle, packet.base)
+        };
+        if value < 0 {
+            return Err(AVCodecContextError::SubtitleError(AVError::from(-value)));
+        }
+        Ok(got_subtitle != 0)
+    }
+
     pub fn set_parameters(&mut self, params: &AVCodecParameters) {
         unsafe {
             ffi::avcodec_parameters_to_context(self.base, params.base);
         }
     }
 
-    pub fn open(&mut self, codec: &AVCodec) {
+    pub fn flush_buffers(&mut self) {
         unsafe {
-            ffi::avcodec_open2(self.base, codec.base, std::ptr::null_mut());
+            ffi::avcodec_flush_buffers(self.base);
         }
     }
+
+    /// Opens the codec, options not consumed by the codec are left in `options`
+    pub fn open(
+        &mut self,
+        codec: &AVCodec,
+        options: Option<&mut AVDictionary>,
+    ) -> Result<(), AVCodecContextError> {
+        AVError::from_errno(unsafe {
+            ffi::avcodec_open2(self.base, codec.base, AVDictionary::as_mut_ptr(options))
+        }).map_err(|err| AVCodecContextError::OpenFailed(err))
+    }
 }
 
 impl Drop for AVCodecContext {
@@ -568,6 +1308,204 @@ impl Drop for AVCodecContext {
     }
 }
 
+pub struct AVSubtitle {
+    base: ffi::AVSubtitle,
+}
+
+impl AVSubtitle {
+    pub fn new() -> Self {
+        return AVSubtitle { base: unsafe { std::mem::zeroed() } };
+    }
+
+    fn clear(&mut self) {
+        unsafe { ffi::avsubtitle_free(&mut self.base) }
+    }
+
+    /// Presentation timestamp in AV_TIME_BASE units
+    pub fn pts(&self) -> Option<i64> {
+        timestamp(self.base.pts)
+    }
+
+    /// Relative to the packet timestamp, in milliseconds
+    pub fn start_display_time(&self) -> u32 {
+        self.base.start_display_time
+    }
+
+    /// Relative to the packet timestamp, in milliseconds
+    pub fn end_display_time(&self) -> u32 {
+        self.base.end_display_time
+    }
+
+    pub fn rects(&self) -> impl Iterator<Item = AVSubtitleRect> {
+        let rects = if self.base.rects.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.base.rects, self.base.num_rects as usize) }
+        };
+        rects
+            .iter()
+            .filter_map(|rect| unsafe { rect.as_ref() })
+            .map(|rect| AVSubtitleRect::new(rect))
+    }
+}
+
+impl Default for AVSubtitle {
+    fn default() -> Self {
+        AVSubtitle::new()
+    }
+}
+
+impl Drop for AVSubtitle {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+pub struct AVSubtitleRect<'a> {
+    base: &'a ffi::AVSubtitleRect,
+}
+
+impl<'a> AVSubtitleRect<'a> {
+    fn new(base: &'a ffi::AVSubtitleRect) -> Self {
+        return AVSubtitleRect { base };
+    }
+
+    pub fn rect_type(&self) -> AVSubtitleType {
+        AVSubtitleType::from_u32(self.base.type_ as u32).unwrap_or(AVSubtitleType::None)
+    }
+
+    pub fn text(&self) -> Option<String> {
+        native_string(self.base.text).ok()
+    }
+
+    /// The event as an ASS dialogue line, set for all text based subtitles
+    pub fn ass(&self) -> Option<String> {
+        native_string(self.base.ass).ok()
+    }
+}
+
+/// Interleaved sample types `SwrContext` can produce
+pub trait SwrSample: Copy + Default {
+    const FORMAT: AVSampleFormat;
+}
+
+impl SwrSample for f32 {
+    const FORMAT: AVSampleFormat = AVSampleFormat::FLT;
+}
+
+impl SwrSample for i16 {
+    const FORMAT: AVSampleFormat = AVSampleFormat::S16;
+}
+
+pub struct SwrContext<T: SwrSample> {
+    base: *mut ffi::SwrContext,
+    out_layout: AVChannelLayout,
+    out_sample_rate: i32,
+    _sample: PhantomData<T>,
+}
+
+#[derive(Error, Debug)]
+pub enum SwrContextError {
+    #[error(transparent)]
+    AllocFailed(#[from] AVAllocError),
+    #[error("Initializing resampler failed")]
+    InitFailed(#[source] AVError),
+    #[error("Converting samples failed")]
+    ConvertFailed(#[source] AVError),
+}
+
+impl<T: SwrSample> SwrContext<T> {
+    /// Converts audio frames shaped like `source` into interleaved samples of type `T`
+    pub fn new(
+        source: &AVFrame,
+        out_layout: AVChannelLayout,
+        out_sample_rate: i32,
+    ) -> Result<Self, SwrContextError> {
+        let in_layout = source.layout();
+
+        let base = unsafe {
+            ffi::swr_alloc_set_opts(
+                std::ptr::null_mut(),
+                out_layout.bits() as i64,
+                T::FORMAT as ffi::AVSampleFormat,
+                out_sample_rate,
+                in_layout.bits() as i64,
+                source.sample_format() as ffi::AVSampleFormat,
+                source.sample_rate(),
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if base.is_null() {
+            return Err(AVAllocError::AllocFailed("SwrContext".to_string()).into());
+        }
+
+        let context = SwrContext {
+            base,
+            out_layout,
+            out_sample_rate,
+            _sample: PhantomData,
+        };
+        AVError::from_errno(unsafe { ffi::swr_init(context.base) })
+            .map_err(|err| SwrContextError::InitFailed(err))?;
+
+        Ok(context)
+    }
+
+    pub fn out_layout(&self) -> AVChannelLayout {
+        self.out_layout
+    }
+
+    pub fn out_sample_rate(&self) -> i32 {
+        self.out_sample_rate
+    }
+
+    pub fn convert(&mut self, source: &AVFrame) -> Result<Vec<T>, SwrContextError> {
+        self.convert_raw(source.extended_data_ptr(), source.nb_samples())
+    }
+
+    /// Drains the samples buffered for resampling, call once at the end of the stream
+    pub fn flush(&mut self) -> Result<Vec<T>, SwrContextError> {
+        self.convert_raw(std::ptr::null(), 0)
+    }
+
+    fn convert_raw(
+        &mut self,
+        input: *const *const u8,
+        in_samples: i32,
+    ) -> Result<Vec<T>, SwrContextError> {
+        let channels = self.out_layout.channels();
+        let capacity = unsafe { ffi::swr_get_out_samples(self.base, in_samples) }.max(0);
+        let mut samples = vec![T::default(); (capacity * channels) as usize];
+        if capacity == 0 {
+            return Ok(samples);
+        }
+
+        let mut output = [samples.as_mut_ptr() as *mut u8];
+        let converted = unsafe {
+            ffi::swr_convert(
+                self.base,
+                output.as_mut_ptr(),
+                capacity,
+                input as *mut *const u8,
+                in_samples,
+            )
+        };
+        if converted < 0 {
+            return Err(SwrContextError::ConvertFailed(AVError::from(-converted)));
+        }
+
+        samples.truncate((converted * channels) as usize);
+        Ok(samples)
+    }
+}
+
+impl<T: SwrSample> Drop for SwrContext<T> {
+    fn drop(&mut self) {
+        unsafe { ffi::swr_free(&mut self.base) }
+    }
+}
+
 pub struct SwsContext {
     base: *mut ffi::SwsContext,
 }
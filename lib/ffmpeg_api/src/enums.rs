@@ -13,6 +13,108 @@ bitflags! {
     }
 }
 
+bitflags! {
+    #[derive(Default)]
+    pub struct AVDisposition: u32 {
+        const DEFAULT = ffi::AV_DISPOSITION_DEFAULT;
+        const DUB = ffi::AV_DISPOSITION_DUB;
+        const ORIGINAL = ffi::AV_DISPOSITION_ORIGINAL;
+        const COMMENT = ffi::AV_DISPOSITION_COMMENT;
+        const LYRICS = ffi::AV_DISPOSITION_LYRICS;
+        const KARAOKE = ffi::AV_DISPOSITION_KARAOKE;
+        const FORCED = ffi::AV_DISPOSITION_FORCED;
+        const HEARING_IMPAIRED = ffi::AV_DISPOSITION_HEARING_IMPAIRED;
+        const VISUAL_IMPAIRED = ffi::AV_DISPOSITION_VISUAL_IMPAIRED;
+        const CLEAN_EFFECTS = ffi::AV_DISPOSITION_CLEAN_EFFECTS;
+        const ATTACHED_PIC = ffi::AV_DISPOSITION_ATTACHED_PIC;
+        const TIMED_THUMBNAILS = ffi::AV_DISPOSITION_TIMED_THUMBNAILS;
+        const CAPTIONS = ffi::AV_DISPOSITION_CAPTIONS;
+        const DESCRIPTIONS = ffi::AV_DISPOSITION_DESCRIPTIONS;
+        const METADATA = ffi::AV_DISPOSITION_METADATA;
+        const DEPENDENT = ffi::AV_DISPOSITION_DEPENDENT;
+        const STILL_IMAGE = ffi::AV_DISPOSITION_STILL_IMAGE;
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct AVSeekFlags: u32 {
+        const BACKWARD = ffi::AVSEEK_FLAG_BACKWARD;
+        const BYTE = ffi::AVSEEK_FLAG_BYTE;
+        const ANY = ffi::AVSEEK_FLAG_ANY;
+        const FRAME = ffi::AVSEEK_FLAG_FRAME;
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct AVChannelLayout: u64 {
+        const FRONT_LEFT = ffi::AV_CH_FRONT_LEFT as u64;
+        const FRONT_RIGHT = ffi::AV_CH_FRONT_RIGHT as u64;
+        const FRONT_CENTER = ffi::AV_CH_FRONT_CENTER as u64;
+        const LOW_FREQUENCY = ffi::AV_CH_LOW_FREQUENCY as u64;
+        const BACK_LEFT = ffi::AV_CH_BACK_LEFT as u64;
+        const BACK_RIGHT = ffi::AV_CH_BACK_RIGHT as u64;
+        const FRONT_LEFT_OF_CENTER = ffi::AV_CH_FRONT_LEFT_OF_CENTER as u64;
+        const FRONT_RIGHT_OF_CENTER = ffi::AV_CH_FRONT_RIGHT_OF_CENTER as u64;
+        const BACK_CENTER = ffi::AV_CH_BACK_CENTER as u64;
+        const SIDE_LEFT = ffi::AV_CH_SIDE_LEFT as u64;
+        const SIDE_RIGHT = ffi::AV_CH_SIDE_RIGHT as u64;
+        const TOP_CENTER = ffi::AV_CH_TOP_CENTER as u64;
+        const TOP_FRONT_LEFT = ffi::AV_CH_TOP_FRONT_LEFT as u64;
+        const TOP_FRONT_CENTER = ffi::AV_CH_TOP_FRONT_CENTER as u64;
+        const TOP_FRONT_RIGHT = ffi::AV_CH_TOP_FRONT_RIGHT as u64;
+        const TOP_BACK_LEFT = ffi::AV_CH_TOP_BACK_LEFT as u64;
+        const TOP_BACK_CENTER = ffi::AV_CH_TOP_BACK_CENTER as u64;
+        const TOP_BACK_RIGHT = ffi::AV_CH_TOP_BACK_RIGHT as u64;
+        const STEREO_LEFT = ffi::AV_CH_STEREO_LEFT as u64;
+        const STEREO_RIGHT = ffi::AV_CH_STEREO_RIGHT as u64;
+        const WIDE_LEFT = ffi::AV_CH_WIDE_LEFT as u64;
+        const WIDE_RIGHT = ffi::AV_CH_WIDE_RIGHT as u64;
+        const SURROUND_DIRECT_LEFT = ffi::AV_CH_SURROUND_DIRECT_LEFT as u64;
+        const SURROUND_DIRECT_RIGHT = ffi::AV_CH_SURROUND_DIRECT_RIGHT as u64;
+        const LOW_FREQUENCY_2 = ffi::AV_CH_LOW_FREQUENCY_2 as u64;
+
+        const LAYOUT_MONO = Self::FRONT_CENTER.bits;
+        const LAYOUT_STEREO = Self::FRONT_LEFT.bits | Self::FRONT_RIGHT.bits;
+        const LAYOUT_2POINT1 = Self::LAYOUT_STEREO.bits | Self::LOW_FREQUENCY.bits;
+        const LAYOUT_SURROUND = Self::LAYOUT_STEREO.bits | Self::FRONT_CENTER.bits;
+        const LAYOUT_4POINT0 = Self::LAYOUT_SURROUND.bits | Self::BACK_CENTER.bits;
+        const LAYOUT_QUAD = Self::LAYOUT_STEREO.bits | Self::BACK_LEFT.bits | Self::BACK_RIGHT.bits;
+        const LAYOUT_5POINT0 = Self::LAYOUT_SURROUND.bits | Self::SIDE_LEFT.bits | Self::SIDE_RIGHT.bits;
+        const LAYOUT_5POINT1 = Self::LAYOUT_5POINT0.bits | Self::LOW_FREQUENCY.bits;
+        const LAYOUT_5POINT0_BACK = Self::LAYOUT_SURROUND.bits | Self::BACK_LEFT.bits | Self::BACK_RIGHT.bits;
+        const LAYOUT_5POINT1_BACK = Self::LAYOUT_5POINT0_BACK.bits | Self::LOW_FREQUENCY.bits;
+        const LAYOUT_7POINT1 = Self::LAYOUT_5POINT1.bits | Self::BACK_LEFT.bits | Self::BACK_RIGHT.bits;
+    }
+}
+
+impl AVChannelLayout {
+    /// The layout FFmpeg assumes for streams that only signal a channel count
+    pub fn default_for(channels: i32) -> Self {
+        Self::from_bits_truncate(unsafe { ffi::av_get_default_channel_layout(channels) } as u64)
+    }
+
+    pub fn channels(&self) -> i32 {
+        self.bits().count_ones() as i32
+    }
+
+    pub fn name(&self) -> String {
+        let mut buffer = [0 as std::os::raw::c_char; 64];
+        unsafe {
+            ffi::av_get_channel_layout_string(
+                buffer.as_mut_ptr(),
+                buffer.len() as std::os::raw::c_int,
+                self.channels(),
+                self.bits(),
+            );
+            std::ffi::CStr::from_ptr(buffer.as_ptr())
+        }
+        .to_string_lossy()
+        .into_owned()
+    }
+}
+
 #[doc = "Pixel format."]
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
@@ -410,6 +512,36 @@ pub enum AVPixelFormat {
     NV42 = ffi::AVPixelFormat_AV_PIX_FMT_NV42,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum AVSampleFormat {
+    NONE = ffi::AVSampleFormat_AV_SAMPLE_FMT_NONE,
+    #[doc = " unsigned 8 bits"]
+    U8 = ffi::AVSampleFormat_AV_SAMPLE_FMT_U8,
+    #[doc = " signed 16 bits"]
+    S16 = ffi::AVSampleFormat_AV_SAMPLE_FMT_S16,
+    #[doc = " signed 32 bits"]
+    S32 = ffi::AVSampleFormat_AV_SAMPLE_FMT_S32,
+    #[doc = " float"]
+    FLT = ffi::AVSampleFormat_AV_SAMPLE_FMT_FLT,
+    #[doc = " double"]
+    DBL = ffi::AVSampleFormat_AV_SAMPLE_FMT_DBL,
+    #[doc = " unsigned 8 bits, planar"]
+    U8P = ffi::AVSampleFormat_AV_SAMPLE_FMT_U8P,
+    #[doc = " signed 16 bits, planar"]
+    S16P = ffi::AVSampleFormat_AV_SAMPLE_FMT_S16P,
+    #[doc = " signed 32 bits, planar"]
+    S32P = ffi::AVSampleFormat_AV_SAMPLE_FMT_S32P,
+    #[doc = " float, planar"]
+    FLTP = ffi::AVSampleFormat_AV_SAMPLE_FMT_FLTP,
+    #[doc = " double, planar"]
+    DBLP = ffi::AVSampleFormat_AV_SAMPLE_FMT_DBLP,
+    #[doc = " signed 64 bits"]
+    S64 = ffi::AVSampleFormat_AV_SAMPLE_FMT_S64,
+    #[doc = " signed 64 bits, planar"]
+    S64P = ffi::AVSampleFormat_AV_SAMPLE_FMT_S64P,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
 #[repr(i32)]
 pub enum AVMediaType {
@@ -894,6 +1026,50 @@ pub enum AVDiscard {
     All = ffi::AVDiscard_AVDISCARD_ALL,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum AVFrameSideDataType {
+    #[doc = " The data is the AVPanScan struct defined in libavcodec."]
+    PanScan = ffi::AVFrameSideDataType_AV_FRAME_DATA_PANSCAN,
+    #[doc = " ATSC A53 Part 4 Closed Captions."]
+    #[doc = " A53 CC bitstream is stored as uint8_t in AVFrameSideData.data."]
+    A53ClosedCaptions = ffi::AVFrameSideDataType_AV_FRAME_DATA_A53_CC,
+    Stereo3D = ffi::AVFrameSideDataType_AV_FRAME_DATA_STEREO3D,
+    MatrixEncoding = ffi::AVFrameSideDataType_AV_FRAME_DATA_MATRIXENCODING,
+    DownmixInfo = ffi::AVFrameSideDataType_AV_FRAME_DATA_DOWNMIX_INFO,
+    ReplayGain = ffi::AVFrameSideDataType_AV_FRAME_DATA_REPLAYGAIN,
+    DisplayMatrix = ffi::AVFrameSideDataType_AV_FRAME_DATA_DISPLAYMATRIX,
+    #[doc = " Active Format Description data consisting of a single byte."]
+    ActiveFormatDescription = ffi::AVFrameSideDataType_AV_FRAME_DATA_AFD,
+    MotionVectors = ffi::AVFrameSideDataType_AV_FRAME_DATA_MOTION_VECTORS,
+    SkipSamples = ffi::AVFrameSideDataType_AV_FRAME_DATA_SKIP_SAMPLES,
+    AudioServiceType = ffi::AVFrameSideDataType_AV_FRAME_DATA_AUDIO_SERVICE_TYPE,
+    MasteringDisplayMetadata = ffi::AVFrameSideDataType_AV_FRAME_DATA_MASTERING_DISPLAY_METADATA,
+    GopTimecode = ffi::AVFrameSideDataType_AV_FRAME_DATA_GOP_TIMECODE,
+    Spherical = ffi::AVFrameSideDataType_AV_FRAME_DATA_SPHERICAL,
+    ContentLightLevel = ffi::AVFrameSideDataType_AV_FRAME_DATA_CONTENT_LIGHT_LEVEL,
+    IccProfile = ffi::AVFrameSideDataType_AV_FRAME_DATA_ICC_PROFILE,
+    QpTableProperties = ffi::AVFrameSideDataType_AV_FRAME_DATA_QP_TABLE_PROPERTIES,
+    QpTableData = ffi::AVFrameSideDataType_AV_FRAME_DATA_QP_TABLE_DATA,
+    S12MTimecode = ffi::AVFrameSideDataType_AV_FRAME_DATA_S12M_TIMECODE,
+    DynamicHdrPlus = ffi::AVFrameSideDataType_AV_FRAME_DATA_DYNAMIC_HDR_PLUS,
+    RegionsOfInterest = ffi::AVFrameSideDataType_AV_FRAME_DATA_REGIONS_OF_INTEREST,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum AVSubtitleType {
+    None = ffi::AVSubtitleType_SUBTITLE_NONE,
+    #[doc = " A bitmap, pict will be set"]
+    Bitmap = ffi::AVSubtitleType_SUBTITLE_BITMAP,
+    #[doc = " Plain text, the text field must be set by the decoder and is"]
+    #[doc = " authoritative. ass and pict fields may contain approximations."]
+    Text = ffi::AVSubtitleType_SUBTITLE_TEXT,
+    #[doc = " Formatted text, the ass field must be set by the decoder and is"]
+    #[doc = " authoritative. pict and text fields may contain approximations."]
+    Ass = ffi::AVSubtitleType_SUBTITLE_ASS,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, FromPrimitive, ToPrimitive)]
 #[repr(u32)]
 pub enum SwsScaler {
@@ -909,3 +1085,17 @@ pub enum SwsScaler {
     Lanczos = ffi::SWS_LANCZOS,
     Spline = ffi::SWS_SPLINE,
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum AVLogLevel {
+    Quiet = ffi::AV_LOG_QUIET,
+    Panic = ffi::AV_LOG_PANIC as i32,
+    Fatal = ffi::AV_LOG_FATAL as i32,
+    Error = ffi::AV_LOG_ERROR as i32,
+    Warning = ffi::AV_LOG_WARNING as i32,
+    Info = ffi::AV_LOG_INFO as i32,
+    Verbose = ffi::AV_LOG_VERBOSE as i32,
+    Debug = ffi::AV_LOG_DEBUG as i32,
+    Trace = ffi::AV_LOG_TRACE as i32,
+}
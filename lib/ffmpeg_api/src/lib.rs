@@ -9,3 +9,4 @@ pub mod enums;
 pub mod err;
 pub mod err_ffi;
 pub mod err_av;
+pub mod log;
@@ -0,0 +1,116 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+
+use ffmpeg_dev::sys as ffi;
+use num_traits::FromPrimitive;
+
+use crate::enums::AVLogLevel;
+
+const LINE_SIZE: usize = 1024;
+
+thread_local! {
+    // FFmpeg emits lines in several pieces, they are buffered until the newline arrives
+    static LINE: RefCell<String> = RefCell::new(String::new());
+}
+
+/// Forwards all FFmpeg log output to the `log` facade instead of stderr.
+/// Records use `ffmpeg::<component>` as target, e.g. `ffmpeg::h264`.
+pub fn init() {
+    unsafe { ffi::av_log_set_callback(Some(log_callback)) }
+}
+
+pub fn set_level(level: AVLogLevel) {
+    unsafe { ffi::av_log_set_level(level as c_int) }
+}
+
+pub fn level() -> Option<AVLogLevel> {
+    AVLogLevel::from_i32(unsafe { ffi::av_log_get_level() })
+}
+
+pub fn level_filter(level: AVLogLevel) -> log::LevelFilter {
+    match level {
+        AVLogLevel::Quiet => log::LevelFilter::Off,
+        AVLogLevel::Panic | AVLogLevel::Fatal | AVLogLevel::Error => log::LevelFilter::Error,
+        AVLogLevel::Warning => log::LevelFilter::Warn,
+        AVLogLevel::Info => log::LevelFilter::Info,
+        AVLogLevel::Verbose | AVLogLevel::Debug => log::LevelFilter::Debug,
+        AVLogLevel::Trace => log::LevelFilter::Trace,
+    }
+}
+
+fn map_level(level: c_int) -> log::Level {
+    if level <= ffi::AV_LOG_ERROR as c_int {
+        log::Level::Error
+    } else if level <= ffi::AV_LOG_WARNING as c_int {
+        log::Level::Warn
+    } else if level <= ffi::AV_LOG_INFO as c_int {
+        log::Level::Info
+    } else if level <= ffi::AV_LOG_DEBUG as c_int {
+        log::Level::Debug
+    } else {
+        log::Level::Trace
+    }
+}
+
+unsafe fn component_name(avcl: *mut c_void) -> Option<String> {
+    let class = (avcl as *const *const ffi::AVClass).as_ref()?.as_ref()?;
+    let name = match class.item_name {
+        Some(item_name) => item_name(avcl),
+        None => class.class_name,
+    };
+    if name.is_null() {
+        return None;
+    }
+
+    Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+}
+
+unsafe extern "C" fn log_callback(
+    avcl: *mut c_void,
+    level: c_int,
+    fmt: *const c_char,
+    vl: *mut ffi::__va_list_tag,
+) {
+    // The upper bits may carry a color, see AV_LOG_C
+    if (level & 0xff) > ffi::av_log_get_level() {
+        return;
+    }
+    let record_level = map_level(level & 0xff);
+    if record_level > log::max_level() {
+        return;
+    }
+
+    let mut buffer = [0 as c_char; LINE_SIZE];
+    let mut print_prefix: c_int = 0;
+    if ffi::av_log_format_line2(
+        avcl,
+        level,
+        fmt,
+        vl,
+        buffer.as_mut_ptr(),
+        LINE_SIZE as c_int,
+        &mut print_prefix,
+    ) < 0
+    {
+        return;
+    }
+    let text = CStr::from_ptr(buffer.as_ptr()).to_string_lossy();
+
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        LINE.with(|line| {
+            let mut line = line.borrow_mut();
+            line.push_str(&text);
+            if !line.ends_with('\n') {
+                return;
+            }
+
+            let target = match component_name(avcl) {
+                Some(name) => format!("ffmpeg::{}", name),
+                None => String::from("ffmpeg"),
+            };
+            log::log!(target: &target, record_level, "{}", line.trim_end());
+            line.clear();
+        })
+    }));
+}
@@ -1,4 +1,39 @@
+use crate::api::AVDictionary;
+use crate::enums::AVChannelLayout;
+
 #[test]
 fn it_works() {
     assert_eq!(2 + 2, 4);
 }
+
+#[test]
+fn dictionary_works() {
+    let mut dictionary = AVDictionary::new();
+    assert!(dictionary.is_empty());
+
+    dictionary.set("title", "Big Buck Bunny").unwrap();
+    dictionary.set("language", "eng").unwrap();
+    assert_eq!(dictionary.len(), 2);
+    assert_eq!(dictionary.get("TITLE"), Some("Big Buck Bunny".to_string()));
+    assert_eq!(dictionary.get("artist"), None);
+    assert_eq!(
+        dictionary.iter().collect::<Vec<_>>(),
+        vec![
+            ("title".to_string(), "Big Buck Bunny".to_string()),
+            ("language".to_string(), "eng".to_string()),
+        ]
+    );
+
+    let copy = dictionary.clone();
+    dictionary.set("title", "Sintel").unwrap();
+    assert_eq!(copy.get("title"), Some("Big Buck Bunny".to_string()));
+}
+
+#[test]
+fn channel_layout_works() {
+    assert_eq!(AVChannelLayout::LAYOUT_MONO.channels(), 1);
+    assert_eq!(AVChannelLayout::LAYOUT_5POINT1.channels(), 6);
+    assert_eq!(AVChannelLayout::LAYOUT_7POINT1.channels(), 8);
+    assert_eq!(AVChannelLayout::default_for(2), AVChannelLayout::LAYOUT_STEREO);
+    assert_eq!(AVChannelLayout::LAYOUT_STEREO.name(), "stereo");
+}
@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use fraction::Fraction;
+
+use crate::media_time::{rescale_rnd, time_base, NANOS_PER_SECOND};
+use crate::{MediaTime, MediaTimeError, Rounding};
+
+impl FromStr for MediaTime {
+    type Err = MediaTimeError;
+
+    /// Parses `hh:mm:ss.mmm` and `mm:ss.mmm` timestamps, plain seconds like `83.5` and
+    /// ISO 8601 durations like `PT1M23.5S`, each optionally preceded by a `-`
+    fn from_str(src: &str) -> Result<MediaTime, MediaTimeError> {
+        let text = src.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text),
+        };
+        let nanos = if text.starts_with('P') {
+            parse_iso8601(text)
+        } else if text.contains(':') {
+            parse_clock(text)
+        } else {
+            parse_decimal(text, 1)
+        };
+        nanos
+            .map(|nanos| if negative { -nanos } else { nanos })
+            .ok_or_else(|| MediaTimeError::InvalidTime(src.to_string()))
+            .and_then(MediaTime::from_nanos_checked)
+    }
+}
+
+impl MediaTime {
+    /// Formats as plain seconds, like `83.5`
+    pub fn to_seconds_string(&self) -> String {
+        let nanos = self.nanoseconds();
+        let sign = if nanos < 0 { "-" } else { "" };
+        format!("{}{}", sign, decimal(nanos.abs()))
+    }
+
+    /// Formats as ISO 8601 duration, like `PT1M23.5S`
+    pub fn to_iso8601(&self) -> String {
+        let nanos = self.nanoseconds();
+        let sign = if nanos < 0 { "-" } else { "" };
+        let nanos = nanos.abs();
+        let hours = nanos / (3600 * NANOS_PER_SECOND);
+        let minutes = nanos / (60 * NANOS_PER_SECOND) % 60;
+        let seconds = nanos % (60 * NANOS_PER_SECOND);
+
+        let mut result = format!("{}PT", sign);
+        if hours > 0 {
+            result.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            result.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || hours == 0 && minutes == 0 {
+            result.push_str(&format!("{}S", decimal(seconds)));
+        }
+        result
+    }
+
+    /// Parses a SMPTE timecode `hh:mm:ss:ff` at the given frame rate, drop-frame timecodes
+    /// use `;` as last separator and need a frame rate of 29.97 or 59.94 fps. The result is
+    /// the first nanosecond of the frame.
+    pub fn from_timecode(
+        timecode: &str,
+        frame_rate: &Fraction,
+    ) -> Result<MediaTime, MediaTimeError> {
+        let rate = FrameRate::new(frame_rate)?;
+        let invalid = || MediaTimeError::InvalidTimecode(timecode.to_string());
+
+        let text = timecode.trim();
+        let (text, frames, drop_frame) = match text.rfind(&[':', ';'][..]) {
+            Some(index) => (
+                &text[..index],
+                &text[index + 1..],
+                &text[index..=index] == ";",
+            ),
+            None => return Err(invalid()),
+        };
+        let fields = text
+            .split(':')
+            .chain(std::iter::once(frames))
+            .map(parse_digits)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let (hours, minutes, seconds, frames) = match fields.as_slice() {
+            [hours, minutes, seconds, frames] => (*hours, *minutes, *seconds, *frames),
+            _ => return Err(invalid()),
+        };
+        if minutes > 59 || seconds > 59 || frames >= rate.nominal {
+            return Err(invalid());
+        }
+
+        let total_minutes = hours * 60 + minutes;
+        let mut frame = ((total_minutes * 60) + seconds) * rate.nominal + frames;
+        if drop_frame {
+            let dropped = rate
+                .dropped_frames()
+                .ok_or(MediaTimeError::DropFrameUnsupported)?;
+            // The dropped frame numbers don't exist
+            if minutes % 10 != 0 && seconds == 0 && frames < dropped {
+                return Err(invalid());
+            }
+            frame -= dropped * (total_minutes - total_minutes / 10);
+        }
+
+        // Rounding up keeps the frame number when formatting the time again
+        let nanos = rescale_rnd(frame, rate.den * NANOS_PER_SECOND, rate.num, Rounding::Up)?;
+        MediaTime::from_nanos_checked(nanos)
+    }
+
+    /// Formats as SMPTE timecode of the frame containing this point in time
+    pub fn to_timecode(
+        &self,
+        frame_rate: &Fraction,
+        drop_frame: bool,
+    ) -> Result<String, MediaTimeError> {
+        let rate = FrameRate::new(frame_rate)?;
+        let mut frame = rescale_rnd(
+            self.nanoseconds(),
+            rate.num,
+            rate.den * NANOS_PER_SECOND,
+            Rounding::Down,
+        )?;
+        if frame < 0 {
+            return Err(MediaTimeError::NegativeTimecode);
+        }
+
+        if drop_frame {
+            let dropped = rate
+                .dropped_frames()
+                .ok_or(MediaTimeError::DropFrameUnsupported)?;
+            let frames_per_minute = rate.nominal * 60 - dropped;
+            let frames_per_ten_minutes = frames_per_minute * 10 + dropped;
+            let ten_minutes = frame / frames_per_ten_minutes;
+            let remainder = frame % frames_per_ten_minutes;
+            // The first minute of every ten keeps all its frame numbers
+            let skipped_minutes = if remainder < dropped {
+                0
+            } else {
+                (remainder - dropped) / frames_per_minute
+            };
+            frame += dropped * (9 * ten_minutes + skipped_minutes);
+        }
+
+        let frames = frame % rate.nominal;
+        let seconds = frame / rate.nominal;
+        Ok(format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            if drop_frame { ';' } else { ':' },
+            frames
+        ))
+    }
+}
+
+struct FrameRate {
+    num: i128,
+    den: i128,
+    /// Frames per second counted by the timecode
+    nominal: i128,
+}
+
+impl FrameRate {
+    fn new(frame_rate: &Fraction) -> Result<FrameRate, MediaTimeError> {
+        let (num, den) = time_base(frame_rate)?;
+        let nominal = rescale_rnd(1, num, den, Rounding::Nearest)?;
+        if nominal == 0 {
+            return Err(MediaTimeError::FrameRateInvalid);
+        }
+        Ok(FrameRate { num, den, nominal })
+    }
+
+    /// Frame numbers skipped at the start of every minute not divisible by ten, which is
+    /// only defined for the NTSC rates 30000/1001 and 60000/1001
+    fn dropped_frames(&self) -> Option<i128> {
+        if self.nominal % 30 == 0 && self.num * 1001 == self.nominal * 1000 * self.den {
+            Some(self.nominal / 15)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_digits(text: &str) -> Option<i128> {
+    if text.is_empty() || text.len() > 18 || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Parses a decimal number of `unit` seconds into nanoseconds, digits beyond nanosecond
+/// precision are ignored
+fn parse_decimal(text: &str, unit: i128) -> Option<i128> {
+    let (whole, fraction) = match text.find(&['.', ','][..]) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+    let mut nanos = parse_digits(whole)?.checked_mul(unit * NANOS_PER_SECOND)?;
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        let mut scale = unit * NANOS_PER_SECOND;
+        for digit in fraction.bytes() {
+            scale /= 10;
+            nanos += (digit - b'0') as i128 * scale;
+        }
+    }
+    Some(nanos)
+}
+
+fn parse_clock(text: &str) -> Option<i128> {
+    let fields = text.split(':').collect::<Vec<_>>();
+    let (hours, minutes, seconds) = match fields.as_slice() {
+        [minutes, seconds] => (0, parse_digits(minutes)?, *seconds),
+        [hours, minutes, seconds] => (parse_digits(hours)?, parse_digits(minutes)?, *seconds),
+        _ => return None,
+    };
+    if fields.len() == 3 && minutes > 59 || seconds.contains(',') {
+        return None;
+    }
+    let seconds = parse_decimal(seconds, 1)?;
+    if seconds >= 60 * NANOS_PER_SECOND {
+        return None;
+    }
+    Some((hours * 60 + minutes).checked_mul(60 * NANOS_PER_SECOND)? + seconds)
+}
+
+/// Parses `PnW` and `PnDTnHnMnS` durations, years and months are rejected as their length
+/// varies
+fn parse_iso8601(text: &str) -> Option<i128> {
+    let mut nanos: i128 = 0;
+    let mut rest = &text[1..];
+    let mut in_time = false;
+    let mut units: &[(char, i128)] = &[('W', 7 * 86400), ('D', 86400)];
+    let mut components = 0;
+    while !rest.is_empty() {
+        if rest.starts_with('T') && !in_time {
+            in_time = true;
+            units = &[('H', 3600), ('M', 60), ('S', 1)];
+            rest = &rest[1..];
+            // A time designator has to be followed by a time component
+            if rest.is_empty() {
+                return None;
+            }
+            continue;
+        }
+        let end = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let designator = rest[end..].chars().next()?;
+        // Components have to appear in order, each at most once
+        let position = units.iter().position(|(unit, _)| *unit == designator)?;
+        nanos = nanos.checked_add(parse_decimal(&rest[..end], units[position].1)?)?;
+        units = &units[position + 1..];
+        rest = &rest[end + 1..];
+        components += 1;
+    }
+    if components == 0 {
+        return None;
+    }
+    Some(nanos)
+}
+
+/// Formats nanoseconds as decimal seconds without trailing zeros
+fn decimal(nanos: i128) -> String {
+    let seconds = nanos / NANOS_PER_SECOND;
+    let fraction = nanos % NANOS_PER_SECOND;
+    if fraction == 0 {
+        return seconds.to_string();
+    }
+    let fraction = format!("{:09}", fraction);
+    format!("{}.{}", seconds, fraction.trim_end_matches('0'))
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests;
 
+mod format;
 mod media_time;
 
 pub use media_time::*;
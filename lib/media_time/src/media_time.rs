@@ -1,6 +1,10 @@
+use std::convert::TryFrom;
+
 use fraction::Fraction;
 use thiserror::Error;
 
+pub(crate) const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
 #[derive(Error, Debug)]
 pub enum MediaTimeError {
     #[error("missing numerator in timebase")]
@@ -9,22 +13,104 @@ pub enum MediaTimeError {
     TimebaseDenominatorMissing,
     #[error("invalid denominator in timebase")]
     TimebaseDenominatorInvalid,
+    #[error("invalid numerator in timebase")]
+    TimebaseNumeratorInvalid,
+    #[error("timestamp out of range")]
+    Overflow,
+    #[error("invalid time {0:?}")]
+    InvalidTime(String),
+    #[error("invalid timecode {0:?}")]
+    InvalidTimecode(String),
+    #[error("invalid frame rate")]
+    FrameRateInvalid,
+    #[error("drop-frame timecodes need a frame rate of 29.97 or 59.94 fps")]
+    DropFrameUnsupported,
+    #[error("timecodes can't be negative")]
+    NegativeTimecode,
+}
+
+/// Rounding of rescaled timestamps, matching FFmpeg's `AVRounding`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    /// Towards zero, `AV_ROUND_ZERO`
+    Zero,
+    /// Away from zero, `AV_ROUND_INF`
+    AwayFromZero,
+    /// Towards negative infinity, `AV_ROUND_DOWN`
+    Down,
+    /// Towards positive infinity, `AV_ROUND_UP`
+    Up,
+    /// To the nearest value, halfway cases away from zero, `AV_ROUND_NEAR_INF`
+    Nearest,
 }
 
+/// Point in time or duration with nanosecond precision
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct MediaTime(time::Duration);
 
 impl MediaTime {
+    /// Converts a timestamp in the given time base, rounding to the nearest nanosecond
     pub fn from_rational(timestamp: i64, base: &Fraction) -> Result<MediaTime, MediaTimeError> {
-        let num: u64 = *base.numer().ok_or(MediaTimeError::TimebaseNumeratorMissing)?;
-        let den: u64 = *base.denom().ok_or(MediaTimeError::TimebaseDenominatorMissing)?;
-        if den == 0 {
-            return Err(MediaTimeError::TimebaseDenominatorInvalid)
+        MediaTime::from_rational_rounded(timestamp, base, Rounding::Nearest)
+    }
+
+    pub fn from_rational_rounded(
+        timestamp: i64,
+        base: &Fraction,
+        rounding: Rounding,
+    ) -> Result<MediaTime, MediaTimeError> {
+        let (num, den) = time_base(base)?;
+        let nanos = rescale_rnd(timestamp as i128, num * NANOS_PER_SECOND, den, rounding)?;
+        MediaTime::from_nanos_checked(nanos)
+    }
+
+    /// Converts to a timestamp in the given time base, like `av_rescale_q_rnd`
+    pub fn to_rational(&self, base: &Fraction, rounding: Rounding) -> Result<i64, MediaTimeError> {
+        let (num, den) = time_base(base)?;
+        if num == 0 {
+            return Err(MediaTimeError::TimebaseNumeratorInvalid);
         }
+        let timestamp = rescale_rnd(self.nanoseconds(), den, num * NANOS_PER_SECOND, rounding)?;
+        i64::try_from(timestamp).map_err(|_| MediaTimeError::Overflow)
+    }
+
+    /// Converts a timestamp between time bases without going through `MediaTime`,
+    /// like `av_rescale_q_rnd`
+    pub fn rescale(
+        timestamp: i64,
+        from: &Fraction,
+        to: &Fraction,
+        rounding: Rounding,
+    ) -> Result<i64, MediaTimeError> {
+        let (from_num, from_den) = time_base(from)?;
+        let (to_num, to_den) = time_base(to)?;
+        if to_num == 0 {
+            return Err(MediaTimeError::TimebaseNumeratorInvalid);
+        }
+        let numerator = from_num
+            .checked_mul(to_den)
+            .ok_or(MediaTimeError::Overflow)?;
+        let denominator = from_den
+            .checked_mul(to_num)
+            .ok_or(MediaTimeError::Overflow)?;
+        let timestamp = rescale_rnd(timestamp as i128, numerator, denominator, rounding)?;
+        i64::try_from(timestamp).map_err(|_| MediaTimeError::Overflow)
+    }
+
+    #[inline(always)]
+    pub fn from_nanos(timestamp: i64) -> MediaTime {
+        MediaTime(time::Duration::nanoseconds(timestamp))
+    }
 
-        Ok(MediaTime(time::Duration::milliseconds(
-            (1000 * timestamp as i128 * num as i128 / den as i128) as i64,
-        )))
+    pub(crate) fn from_nanos_checked(timestamp: i128) -> Result<MediaTime, MediaTimeError> {
+        i64::try_from(timestamp)
+            .map(MediaTime::from_nanos)
+            .map_err(|_| MediaTimeError::Overflow)
+    }
+
+    #[inline(always)]
+    pub fn from_micros(timestamp: i64) -> MediaTime {
+        MediaTime(time::Duration::microseconds(timestamp))
     }
 
     #[inline(always)]
@@ -42,6 +128,11 @@ impl MediaTime {
         self.0.is_zero()
     }
 
+    #[inline(always)]
+    pub fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+
     #[inline(always)]
     pub fn seconds(&self) -> i64 {
         self.0.whole_seconds()
@@ -63,18 +154,62 @@ impl MediaTime {
     }
 }
 
+pub(crate) fn time_base(base: &Fraction) -> Result<(i128, i128), MediaTimeError> {
+    let num: u64 = *base
+        .numer()
+        .ok_or(MediaTimeError::TimebaseNumeratorMissing)?;
+    let den: u64 = *base
+        .denom()
+        .ok_or(MediaTimeError::TimebaseDenominatorMissing)?;
+    if den == 0 {
+        return Err(MediaTimeError::TimebaseDenominatorInvalid);
+    }
+    Ok((num as i128, den as i128))
+}
+
+/// Computes `value * numerator / denominator` without intermediate rounding
+pub(crate) fn rescale_rnd(
+    value: i128,
+    numerator: i128,
+    denominator: i128,
+    rounding: Rounding,
+) -> Result<i128, MediaTimeError> {
+    let value = value
+        .checked_mul(numerator)
+        .ok_or(MediaTimeError::Overflow)?;
+    let quotient = value.div_euclid(denominator);
+    let remainder = value.rem_euclid(denominator);
+    if remainder == 0 {
+        return Ok(quotient);
+    }
+
+    // The quotient is rounded down, as the denominator is positive
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Zero => value < 0,
+        Rounding::AwayFromZero => value > 0,
+        Rounding::Nearest => {
+            2 * remainder > denominator || (2 * remainder == denominator && value > 0)
+        }
+    };
+    Ok(if round_up { quotient + 1 } else { quotient })
+}
+
 impl std::fmt::Display for MediaTime {
     #[inline(always)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let z = self.0.subsec_milliseconds();
-        let s = self.0.whole_seconds() % 60;
-        let m = self.0.whole_seconds() / 60 % 60;
-        let h = self.0.whole_seconds() / 3600;
+        let sign = if self.0.is_negative() { "-" } else { "" };
+        let duration = self.0.abs();
+        let z = duration.subsec_milliseconds();
+        let s = duration.whole_seconds() % 60;
+        let m = duration.whole_seconds() / 60 % 60;
+        let h = duration.whole_seconds() / 3600;
 
         if h == 0 {
-            write!(f, "{:02}:{:02}.{:03}", m, s, z)
+            write!(f, "{}{:02}:{:02}.{:03}", sign, m, s, z)
         } else {
-            write!(f, "{:02}:{:02}:{:02}.{:03}", h, m, s, z)
+            write!(f, "{}{:02}:{:02}:{:02}.{:03}", sign, h, m, s, z)
         }
     }
 }
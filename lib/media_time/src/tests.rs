@@ -1,4 +1,4 @@
-use crate::MediaTime;
+use crate::{MediaTime, MediaTimeError, Rounding};
 use fraction::Fraction;
 
 #[test]
@@ -17,7 +17,267 @@ fn from_seconds_works() {
 
 #[test]
 fn from_rational_works() {
-    assert_eq!(MediaTime::from_rational(0, &Fraction::new(1u64, 1u64)).unwrap().seconds(), 0);
-    assert_eq!(MediaTime::from_rational(1357, &Fraction::new(1u64, 1u64)).unwrap().seconds(), 1357);
-    assert_eq!(MediaTime::from_rational(30, &Fraction::new(1u64, 3u64)).unwrap().seconds(), 10);
+    assert_eq!(
+        MediaTime::from_rational(0, &Fraction::new(1u64, 1u64))
+            .unwrap()
+            .seconds(),
+        0
+    );
+    assert_eq!(
+        MediaTime::from_rational(1357, &Fraction::new(1u64, 1u64))
+            .unwrap()
+            .seconds(),
+        1357
+    );
+    assert_eq!(
+        MediaTime::from_rational(30, &Fraction::new(1u64, 3u64))
+            .unwrap()
+            .seconds(),
+        10
+    );
+}
+
+#[test]
+fn from_rational_keeps_precision() {
+    let ntsc = Fraction::new(1001u64, 30000u64);
+    assert_eq!(
+        MediaTime::from_rational(1, &ntsc).unwrap().nanoseconds(),
+        33_366_667
+    );
+    assert_eq!(
+        MediaTime::from_rational_rounded(1, &ntsc, Rounding::Down)
+            .unwrap()
+            .nanoseconds(),
+        33_366_666
+    );
+    // One hour of frames doesn't drift
+    assert_eq!(
+        MediaTime::from_rational(107_892, &ntsc)
+            .unwrap()
+            .nanoseconds(),
+        3_599_996_400_000
+    );
+    assert!(MediaTime::from_rational(1, &Fraction::new(1u64, 0u64)).is_err());
+}
+
+#[test]
+fn to_rational_works() {
+    let ntsc = Fraction::new(1001u64, 30000u64);
+    for pts in &[0i64, 1, 29, 30, 107_892, -5] {
+        let time = MediaTime::from_rational(*pts, &ntsc).unwrap();
+        assert_eq!(time.to_rational(&ntsc, Rounding::Nearest).unwrap(), *pts);
+    }
+
+    let millis = Fraction::new(1u64, 1000u64);
+    let time = MediaTime::from_nanos(1_500_000);
+    assert_eq!(time.to_rational(&millis, Rounding::Zero).unwrap(), 1);
+    assert_eq!(
+        time.to_rational(&millis, Rounding::AwayFromZero).unwrap(),
+        2
+    );
+    assert_eq!(time.to_rational(&millis, Rounding::Down).unwrap(), 1);
+    assert_eq!(time.to_rational(&millis, Rounding::Up).unwrap(), 2);
+    assert_eq!(time.to_rational(&millis, Rounding::Nearest).unwrap(), 2);
+
+    let time = MediaTime::from_nanos(-1_500_000);
+    assert_eq!(time.to_rational(&millis, Rounding::Zero).unwrap(), -1);
+    assert_eq!(
+        time.to_rational(&millis, Rounding::AwayFromZero).unwrap(),
+        -2
+    );
+    assert_eq!(time.to_rational(&millis, Rounding::Down).unwrap(), -2);
+    assert_eq!(time.to_rational(&millis, Rounding::Up).unwrap(), -1);
+    assert_eq!(time.to_rational(&millis, Rounding::Nearest).unwrap(), -2);
+}
+
+#[test]
+fn rescale_works() {
+    let ntsc = Fraction::new(1001u64, 30000u64);
+    let mpegts = Fraction::new(1u64, 90000u64);
+    assert_eq!(
+        MediaTime::rescale(30, &ntsc, &mpegts, Rounding::Nearest).unwrap(),
+        90090
+    );
+    assert_eq!(
+        MediaTime::rescale(1, &mpegts, &ntsc, Rounding::Down).unwrap(),
+        0
+    );
+    assert_eq!(
+        MediaTime::rescale(1, &mpegts, &ntsc, Rounding::Up).unwrap(),
+        1
+    );
+    assert!(MediaTime::rescale(
+        i64::MAX,
+        &Fraction::new(1u64, 1u64),
+        &mpegts,
+        Rounding::Nearest
+    )
+    .is_err());
+}
+
+#[test]
+fn from_str_works() {
+    assert_eq!(
+        "01:23.500".parse::<MediaTime>().unwrap(),
+        MediaTime::from_millis(83_500)
+    );
+    assert_eq!(
+        "00:01:23.500".parse::<MediaTime>().unwrap(),
+        MediaTime::from_millis(83_500)
+    );
+    assert_eq!(
+        "1:00:00".parse::<MediaTime>().unwrap(),
+        MediaTime::from_seconds(3600)
+    );
+    assert_eq!(
+        "83.5".parse::<MediaTime>().unwrap(),
+        MediaTime::from_millis(83_500)
+    );
+    assert_eq!(
+        "2".parse::<MediaTime>().unwrap(),
+        MediaTime::from_seconds(2)
+    );
+    assert_eq!(
+        "0.000000001".parse::<MediaTime>().unwrap(),
+        MediaTime::from_nanos(1)
+    );
+    assert_eq!(
+        "PT1M23.5S".parse::<MediaTime>().unwrap(),
+        MediaTime::from_millis(83_500)
+    );
+    assert_eq!(
+        "P1DT1H".parse::<MediaTime>().unwrap(),
+        MediaTime::from_seconds(90_000)
+    );
+    assert_eq!(
+        "PT0,5H".parse::<MediaTime>().unwrap(),
+        MediaTime::from_seconds(1800)
+    );
+
+    for invalid in &[
+        "",
+        "abc",
+        "--1",
+        "- 1",
+        ".5",
+        "1.",
+        "01:60.000",
+        "01:60:00",
+        "1:2:3:4",
+        "P",
+        "PT",
+        "P1M",
+        "PT1S1M",
+        "PT1H1H",
+    ] {
+        assert!(
+            invalid.parse::<MediaTime>().is_err(),
+            "{:?} should be invalid",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn formatting_round_trips() {
+    for time in &[
+        MediaTime::from_millis(83_500),
+        MediaTime::from_seconds(3600),
+        MediaTime::from_millis(0),
+    ] {
+        assert_eq!(time.to_string().parse::<MediaTime>().unwrap(), *time);
+        assert_eq!(
+            time.to_seconds_string().parse::<MediaTime>().unwrap(),
+            *time
+        );
+        assert_eq!(time.to_iso8601().parse::<MediaTime>().unwrap(), *time);
+    }
+    assert_eq!(MediaTime::from_millis(83_500).to_seconds_string(), "83.5");
+    assert_eq!(MediaTime::from_nanos(1).to_seconds_string(), "0.000000001");
+    assert_eq!(MediaTime::from_millis(83_500).to_iso8601(), "PT1M23.5S");
+    assert_eq!(MediaTime::from_seconds(3600).to_iso8601(), "PT1H");
+    assert_eq!(MediaTime::from_millis(0).to_iso8601(), "PT0S");
+}
+
+#[test]
+fn timecode_works() {
+    let pal = Fraction::new(25u64, 1u64);
+    let time = MediaTime::from_timecode("01:00:00:12", &pal).unwrap();
+    assert_eq!(time, MediaTime::from_millis(3_600_480));
+    assert_eq!(time.to_timecode(&pal, false).unwrap(), "01:00:00:12");
+    assert!(MediaTime::from_timecode("00:00:00:25", &pal).is_err());
+    assert!(MediaTime::from_timecode("00:00:00;00", &pal).is_err());
+    assert!(time.to_timecode(&pal, true).is_err());
+    assert!(matches!(
+        MediaTime::from_millis(-40).to_timecode(&pal, false),
+        Err(MediaTimeError::NegativeTimecode)
+    ));
+    // The frame containing -1ns starts before zero
+    assert!(matches!(
+        MediaTime::from_nanos(-1).to_timecode(&pal, false),
+        Err(MediaTimeError::NegativeTimecode)
+    ));
+
+    // Frame 1800 is the first one of the second minute, which skips two frame numbers
+    let ntsc = Fraction::new(30000u64, 1001u64);
+    let frame = |frame: u64| {
+        MediaTime::from_rational_rounded(
+            frame as i64,
+            &Fraction::new(1001u64, 30000u64),
+            Rounding::Up,
+        )
+        .unwrap()
+    };
+    assert_eq!(frame(1799).to_timecode(&ntsc, true).unwrap(), "00:00:59;29");
+    assert_eq!(frame(1800).to_timecode(&ntsc, true).unwrap(), "00:01:00;02");
+    assert_eq!(
+        frame(17982).to_timecode(&ntsc, true).unwrap(),
+        "00:10:00;00"
+    );
+    assert_eq!(
+        frame(107_892).to_timecode(&ntsc, true).unwrap(),
+        "01:00:00;00"
+    );
+    assert_eq!(
+        frame(1800).to_timecode(&ntsc, false).unwrap(),
+        "00:01:00:00"
+    );
+    assert_eq!(
+        MediaTime::from_timecode("00:01:00;02", &ntsc).unwrap(),
+        frame(1800)
+    );
+    assert_eq!(
+        MediaTime::from_timecode("01:00:00;00", &ntsc).unwrap(),
+        frame(107_892)
+    );
+    assert!(MediaTime::from_timecode("00:01:00;00", &ntsc).is_err());
+    assert!(MediaTime::from_timecode("00:10:00;00", &ntsc).is_ok());
+
+    let ntsc_double = Fraction::new(60000u64, 1001u64);
+    for index in (0..250_000).step_by(997) {
+        let timecode = frame(index * 2).to_timecode(&ntsc_double, true).unwrap();
+        let time = MediaTime::from_timecode(&timecode, &ntsc_double).unwrap();
+        assert_eq!(time.to_timecode(&ntsc_double, true).unwrap(), timecode);
+    }
+    assert_eq!(
+        frame(1800).to_timecode(&ntsc_double, true).unwrap(),
+        "00:01:00;04"
+    );
+}
+
+#[test]
+fn negative_times_work() {
+    let time = MediaTime::from_millis(-83_500);
+    assert!(time.is_negative());
+    assert_eq!(time.to_string(), "-01:23.500");
+    assert_eq!(MediaTime::from_millis(-500).to_string(), "-00:00.500");
+    assert_eq!(MediaTime::from_seconds(-3600).to_string(), "-01:00:00.000");
+    assert_eq!(time.to_seconds_string(), "-83.5");
+    assert_eq!(time.to_iso8601(), "-PT1M23.5S");
+    assert_eq!("-01:23.500".parse::<MediaTime>().unwrap(), time);
+    assert_eq!("-83.5".parse::<MediaTime>().unwrap(), time);
+    assert_eq!("-PT1M23.5S".parse::<MediaTime>().unwrap(), time);
+    assert!(time
+        .to_timecode(&Fraction::new(25u64, 1u64), false)
+        .is_err());
 }
@@ -0,0 +1,416 @@
+//! ASS and SSA import. Styles are reduced to their color and font flags, which become
+//! `::cue` classes, and to their alignment, which becomes cue settings.
+
+use media_time::MediaTime;
+
+use crate::parser::{split_lines, strip_bom, timestamp_millis, Line};
+use crate::settings::{Align, CueSettings, LineAlign, LinePosition};
+use crate::webvtt::{WebVTTBlock, WebVTTCue, WebVTTFile};
+use crate::{SubtitleImportError, SubtitleImportErrorKind};
+
+const DEFAULT_STYLE_FORMAT: &str = "Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, \
+    OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, \
+    Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+const DEFAULT_EVENT_FORMAT: &str =
+    "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+struct Style {
+    name: String,
+    class: String,
+    rules: Vec<String>,
+    alignment: Option<u8>,
+}
+
+struct Event {
+    start: MediaTime,
+    end: MediaTime,
+    style: String,
+    text: String,
+}
+
+pub fn parse(input: &str) -> Result<WebVTTFile, SubtitleImportError> {
+    let lines = split_lines(strip_bom(input));
+
+    let mut section = String::new();
+    let mut has_events = false;
+    let mut style_format = split_format(DEFAULT_STYLE_FORMAT);
+    let mut event_format = split_format(DEFAULT_EVENT_FORMAT);
+    let mut styles = Vec::new();
+    let mut events = Vec::new();
+    for line in &lines {
+        let text = line.text.trim();
+        if text.starts_with('[') && text.ends_with(']') {
+            section = text.to_ascii_lowercase();
+            has_events |= section == "[events]";
+            continue;
+        }
+        let (key, value) = match text.find(':') {
+            Some(position) => (&text[..position], text[position + 1..].trim_start()),
+            None => continue,
+        };
+
+        match (section.as_str(), key) {
+            ("[v4+ styles]", "Format") | ("[v4 styles]", "Format") => {
+                style_format = split_format(value)
+            }
+            ("[v4+ styles]", "Style") => styles.push(parse_style(value, &style_format, false)),
+            // SSA uses a different alignment numbering
+            ("[v4 styles]", "Style") => styles.push(parse_style(value, &style_format, true)),
+            ("[events]", "Format") => event_format = split_format(value),
+            ("[events]", "Dialogue") => events.push(parse_event(*line, value, &event_format)?),
+            _ => {}
+        }
+    }
+    if !has_events {
+        return Err(SubtitleImportError {
+            line: lines.len() + 1,
+            column: 1,
+            kind: SubtitleImportErrorKind::MissingEvents,
+        });
+    }
+
+    // WebVTT requires cues ordered by their start, while ASS events are ordered by layer
+    events.sort_by_key(|event| event.start);
+
+    let mut file = WebVTTFile::new();
+    let used_styles = styles
+        .iter()
+        .filter(|style| !style.rules.is_empty())
+        .filter(|style| {
+            events.iter().any(|event| {
+                find_style(&styles, &event.style).is_some_and(|used| std::ptr::eq(used, *style))
+            })
+        })
+        .map(|style| {
+            format!(
+                "::cue(.{}) {{\n{}\n}}",
+                style.class,
+                style
+                    .rules
+                    .iter()
+                    .map(|rule| format!("  {};", rule))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        })
+        .collect::<Vec<_>>();
+    if !used_styles.is_empty() {
+        file.add_block(WebVTTBlock::Style(used_styles.join("\n")));
+    }
+
+    for event in events {
+        let style = find_style(&styles, &event.style);
+        let (text, mut settings) = convert_text(&event.text);
+        let text = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.is_empty() || event.end <= event.start {
+            continue;
+        }
+        if settings.is_empty() {
+            if let Some(alignment) = style.and_then(|style| style.alignment) {
+                settings = alignment_settings(alignment);
+            }
+        }
+        let payload = match style {
+            Some(style) if !style.rules.is_empty() => format!("<c.{}>{}</c>", style.class, text),
+            _ => text,
+        };
+        file.add(WebVTTCue::new(event.start, event.end, payload).with_settings(settings));
+    }
+
+    Ok(file)
+}
+
+fn split_format(format: &str) -> Vec<String> {
+    format
+        .split(',')
+        .map(|field| field.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// Splits the fields of a line, the last one may contain commas
+fn split_fields<'a>(value: &'a str, format: &'a [String]) -> Vec<(&'a str, &'a str)> {
+    format
+        .iter()
+        .map(String::as_str)
+        .zip(value.splitn(format.len(), ','))
+        .collect()
+}
+
+fn field<'a>(fields: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| value.trim())
+}
+
+fn find_style<'a>(styles: &'a [Style], name: &str) -> Option<&'a Style> {
+    // Renderers fall back to the default style for unknown names
+    let name = name.trim_start_matches('*');
+    styles
+        .iter()
+        .find(|style| style.name == name)
+        .or_else(|| styles.iter().find(|style| style.name == "Default"))
+}
+
+fn parse_style(value: &str, format: &[String], legacy: bool) -> Style {
+    let fields = split_fields(value, format);
+    let name = field(&fields, "name").unwrap_or("").to_string();
+    let flag = |name: &str| {
+        field(&fields, name)
+            .and_then(|value| value.parse::<i32>().ok())
+            .is_some_and(|value| value != 0)
+    };
+
+    let mut rules = Vec::new();
+    if let Some(color) = field(&fields, "primarycolour").and_then(parse_color) {
+        rules.push(format!("color: {}", color));
+    }
+    if flag("bold") {
+        rules.push("font-weight: bold".to_string());
+    }
+    if flag("italic") {
+        rules.push("font-style: italic".to_string());
+    }
+    match (flag("underline"), flag("strikeout")) {
+        (true, true) => rules.push("text-decoration: underline line-through".to_string()),
+        (true, false) => rules.push("text-decoration: underline".to_string()),
+        (false, true) => rules.push("text-decoration: line-through".to_string()),
+        (false, false) => {}
+    }
+
+    let alignment = field(&fields, "alignment")
+        .and_then(|value| value.parse().ok())
+        .map(|alignment| {
+            if legacy {
+                legacy_alignment(alignment)
+            } else {
+                alignment
+            }
+        });
+
+    Style {
+        class: class_name(&name),
+        name,
+        rules,
+        alignment,
+    }
+}
+
+/// Style names may contain anything, while class names are limited
+fn class_name(name: &str) -> String {
+    let class: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if class.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        class
+    } else {
+        format!("s{}", class)
+    }
+}
+
+/// Colors are written as `&HAABBGGRR` or as decimal number with the same layout,
+/// the alpha channel counts up from opaque
+fn parse_color(value: &str) -> Option<String> {
+    let value = value.trim_end_matches('&');
+    let color = match value
+        .strip_prefix("&H")
+        .or_else(|| value.strip_prefix("&h"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<i64>().ok()? as u32,
+    };
+    let [red, green, blue, alpha] = color.to_le_bytes();
+    Some(if alpha == 0 {
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    } else {
+        format!(
+            "rgba({}, {}, {}, {:.2})",
+            red,
+            green,
+            blue,
+            f64::from(255 - alpha) / 255.0
+        )
+    })
+}
+
+fn parse_event(line: Line, value: &str, format: &[String]) -> Result<Event, SubtitleImportError> {
+    let fields = split_fields(value, format);
+    let timestamp = |name: &str| {
+        let value = field(&fields, name).unwrap_or("");
+        parse_timestamp(value).ok_or_else(|| {
+            // Fields are slices of the line, which gives their position
+            let position = if value.is_empty() {
+                line.text.len()
+            } else {
+                value.as_ptr() as usize - line.text.as_ptr() as usize
+            };
+            line.import_error(position, SubtitleImportErrorKind::InvalidTimestamp)
+        })
+    };
+
+    Ok(Event {
+        start: timestamp("start")?,
+        end: timestamp("end")?,
+        style: field(&fields, "style").unwrap_or("").to_string(),
+        text: field(&fields, "text").unwrap_or("").to_string(),
+    })
+}
+
+/// Parses `h:mm:ss.cc`, the fraction is usually given in centiseconds
+fn parse_timestamp(value: &str) -> Option<MediaTime> {
+    // Signs are rejected, `parse` alone would accept them
+    let digits = |text: &str| -> Option<i64> {
+        if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        text.parse().ok()
+    };
+
+    let mut parts = value.splitn(3, ':');
+    let hours = digits(parts.next()?)?;
+    let minutes = digits(parts.next()?)?;
+    let seconds = parts.next()?;
+    let (seconds, fraction) = match seconds.find('.') {
+        Some(position) => (&seconds[..position], &seconds[position + 1..]),
+        None => (seconds, ""),
+    };
+    let seconds = digits(seconds)?;
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let millis = format!("{:0<3}", fraction)[..3].parse::<i64>().ok()?;
+
+    timestamp_millis(hours, minutes, seconds, millis)
+}
+
+/// Converts ASS text to a WebVTT payload, keeping bold, italic, underline and the alignment
+pub fn convert_text(text: &str) -> (String, CueSettings) {
+    let mut result = String::new();
+    let mut settings = CueSettings::default();
+    let mut open_tags: Vec<char> = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let block: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                for tag in block.split('\\').skip(1) {
+                    let tag = tag.trim();
+                    if let Some(alignment) =
+                        tag.strip_prefix("an").and_then(|value| value.parse().ok())
+                    {
+                        settings = alignment_settings(alignment);
+                    } else if let Some(alignment) =
+                        tag.strip_prefix('a').and_then(|value| value.parse().ok())
+                    {
+                        settings = alignment_settings(legacy_alignment(alignment));
+                    } else {
+                        apply_override(tag, &mut open_tags, &mut result);
+                    }
+                }
+            }
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    result.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    result.push('\u{a0}');
+                }
+                _ => result.push('\\'),
+            },
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            c => result.push(c),
+        }
+    }
+    close_tags(&mut open_tags, 0, &mut result);
+
+    (result, settings)
+}
+
+/// Maps `\an` alignments, which are laid out like a numpad, bottom center is the default
+pub fn alignment_settings(alignment: u8) -> CueSettings {
+    if !(1..=9).contains(&alignment) {
+        return CueSettings::default();
+    }
+    CueSettings {
+        line: match (alignment - 1) / 3 {
+            1 => Some((LinePosition::Percentage(50.0), Some(LineAlign::Center))),
+            2 => Some((LinePosition::Number(0.0), None)),
+            _ => None,
+        },
+        align: match (alignment - 1) % 3 {
+            0 => Some(Align::Left),
+            2 => Some(Align::Right),
+            _ => None,
+        },
+        ..CueSettings::default()
+    }
+}
+
+/// SSA alignments add 4 for top and 8 for middle instead of using a numpad layout
+fn legacy_alignment(alignment: u8) -> u8 {
+    match alignment {
+        1..=3 => alignment,
+        5..=7 => alignment + 2,
+        9..=11 => alignment - 5,
+        _ => 0,
+    }
+}
+
+fn apply_override(tag: &str, open_tags: &mut Vec<char>, result: &mut String) {
+    if tag == "r" {
+        close_tags(open_tags, 0, result);
+        return;
+    }
+
+    let mut chars = tag.chars();
+    let name = match chars.next() {
+        Some(name @ 'b') | Some(name @ 'i') | Some(name @ 'u') => name,
+        _ => return,
+    };
+    // Bold may also carry a font weight, everything but 0 enables it
+    let enabled = match chars.as_str().parse::<u32>() {
+        Ok(value) => value != 0,
+        Err(_) => return,
+    };
+
+    match open_tags.iter().position(|&open| open == name) {
+        None if enabled => {
+            result.push_str(&format!("<{}>", name));
+            open_tags.push(name);
+        }
+        Some(position) if !enabled => {
+            // Tags have to be closed in order, the ones opened later are reopened
+            let reopen: Vec<char> = open_tags[position + 1..].to_vec();
+            close_tags(open_tags, position, result);
+            for name in reopen {
+                result.push_str(&format!("<{}>", name));
+                open_tags.push(name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn close_tags(open_tags: &mut Vec<char>, from: usize, result: &mut String) {
+    while open_tags.len() > from {
+        if let Some(name) = open_tags.pop() {
+            result.push_str(&format!("</{}>", name));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Splits a file into segments for HLS, along with the media playlist referencing them
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use media_time::MediaTime;
+
+use crate::webvtt::{WebVTTBlock, WebVTTError, WebVTTFile};
+
+pub struct HlsSegmenter {
+    target_duration: MediaTime,
+    mpegts_offset: u64,
+}
+
+#[derive(Error, Debug, Copy, Clone, PartialEq)]
+pub enum HlsError {
+    #[error("Target duration has to be at least a millisecond, got {0}")]
+    TargetDurationTooShort(MediaTime),
+}
+
+pub struct HlsSegment {
+    pub start: MediaTime,
+    pub duration: MediaTime,
+    pub file: WebVTTFile,
+}
+
+impl HlsSegmenter {
+    pub fn new(target_duration: MediaTime) -> Result<HlsSegmenter, HlsError> {
+        // Segments are cut at whole milliseconds
+        if target_duration < MediaTime::from_millis(1) {
+            return Err(HlsError::TargetDurationTooShort(target_duration));
+        }
+        Ok(HlsSegmenter {
+            target_duration,
+            mpegts_offset: 0,
+        })
+    }
+
+    /// MPEG-TS timestamp of media time zero, which has to match the first PTS of the
+    /// media segments, e.g. 126000 for FFmpeg's default start offset of 1.4 seconds
+    pub fn with_mpegts_offset(mut self, mpegts_offset: u64) -> HlsSegmenter {
+        self.mpegts_offset = mpegts_offset;
+        self
+    }
+
+    pub fn target_duration(&self) -> MediaTime {
+        self.target_duration
+    }
+
+    pub fn mpegts_offset(&self) -> u64 {
+        self.mpegts_offset
+    }
+
+    /// Splits `file` into segments covering `duration`, with additional segments for cues
+    /// ending after it. Cues keep their timing and are repeated in every segment they
+    /// overlap, styles and regions are repeated in all segments and comments are dropped.
+    pub fn segment(&self, file: &WebVTTFile, duration: MediaTime) -> Vec<HlsSegment> {
+        let target = self.target_duration.milliseconds();
+        // Players only show cues within the time window of their segment
+        let total = file
+            .cues()
+            .map(|cue| cue.end().milliseconds())
+            .chain(std::iter::once(duration.milliseconds()))
+            .max()
+            .unwrap_or(0);
+        let count = std::cmp::max((total + target - 1) / target, 1);
+
+        (0..count)
+            .map(|index| {
+                let start = index * target;
+                let end = std::cmp::min(start + target, std::cmp::max(total, start));
+
+                let mut segment = WebVTTFile::new();
+                segment.set_description(file.description().map(String::from));
+                for line in file.header() {
+                    if !line.starts_with("X-TIMESTAMP-MAP=") {
+                        segment.add_header(line.clone());
+                    }
+                }
+                segment.add_header(format!(
+                    "X-TIMESTAMP-MAP=MPEGTS:{},LOCAL:00:00:00.000",
+                    self.mpegts_offset
+                ));
+                for block in file.blocks() {
+                    let keep = match block {
+                        WebVTTBlock::Cue(cue) => {
+                            let (cue_start, cue_end) =
+                                (cue.start().milliseconds(), cue.end().milliseconds());
+                            // Empty cues at the very end belong to the last segment
+                            cue_start < end && cue_end > start
+                                || index == count - 1 && cue_start >= end
+                        }
+                        WebVTTBlock::Style(_) | WebVTTBlock::Region(_) => true,
+                        WebVTTBlock::Note(_) => false,
+                    };
+                    if keep {
+                        segment.add_block(block.clone());
+                    }
+                }
+
+                HlsSegment {
+                    start: MediaTime::from_millis(start as i64),
+                    duration: MediaTime::from_millis((end - start) as i64),
+                    file: segment,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the segments as `<name>-<index>.vtt` and the playlist as `<name>.m3u8`,
+    /// the playlist is the last of the returned paths
+    pub fn save(
+        &self,
+        file: &WebVTTFile,
+        duration: MediaTime,
+        output_path: impl AsRef<Path>,
+        name: &str,
+    ) -> Result<Vec<PathBuf>, WebVTTError> {
+        let output_path = output_path.as_ref();
+        let segments = self.segment(file, duration);
+
+        let mut paths = Vec::new();
+        let mut names = Vec::new();
+        for (index, segment) in segments.iter().enumerate() {
+            let segment_name = format!("{}-{}.vtt", name, index);
+            let path = output_path.join(&segment_name);
+            segment.file.save(&path)?;
+            paths.push(path);
+            names.push(segment_name);
+        }
+
+        let path = output_path.join(format!("{}.m3u8", name));
+        let playlist = self.playlist(&segments, &names);
+        File::create(&path)
+            .and_then(|file| {
+                let mut file = BufWriter::new(file);
+                file.write_all(playlist.as_bytes())?;
+                file.flush()
+            })
+            .map_err(|err| WebVTTError::IoError(path.clone(), err))?;
+        paths.push(path);
+
+        Ok(paths)
+    }
+
+    /// Media playlist for segments saved under the given URIs
+    pub fn playlist(&self, segments: &[HlsSegment], uris: &[String]) -> String {
+        // The target duration is an integer that no segment may exceed
+        let target_duration = segments
+            .iter()
+            .map(|segment| segment.duration.milliseconds())
+            .chain(std::iter::once(self.target_duration.milliseconds()))
+            .max()
+            .map_or(0, |millis| (millis + 999) / 1000);
+
+        let mut playlist = format!(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+            target_duration
+        );
+        for (segment, uri) in segments.iter().zip(uris) {
+            let millis = segment.duration.milliseconds();
+            playlist.push_str(&format!(
+                "#EXTINF:{}.{:03},\n{}\n",
+                millis / 1000,
+                millis % 1000,
+                uri
+            ));
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        playlist
+    }
+}
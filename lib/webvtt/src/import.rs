@@ -0,0 +1,42 @@
+use thiserror::Error;
+
+use crate::parser::Line;
+
+/// Error of the SubRip, ASS and TTML importers, positions are given in the imported file
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("Invalid subtitles at line {line}, column {column}: {kind}")]
+pub struct SubtitleImportError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: SubtitleImportErrorKind,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SubtitleImportErrorKind {
+    #[error("missing cue timings")]
+    MissingTimings,
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+    #[error("expected \"-->\"")]
+    MissingArrow,
+    #[error("missing [Events] section")]
+    MissingEvents,
+    #[error("invalid XML")]
+    InvalidXml,
+    #[error("missing tt root element")]
+    MissingTtRoot,
+}
+
+impl<'a> Line<'a> {
+    pub(crate) fn import_error(
+        &self,
+        position: usize,
+        kind: SubtitleImportErrorKind,
+    ) -> SubtitleImportError {
+        SubtitleImportError {
+            line: self.number,
+            column: self.column(position),
+            kind,
+        }
+    }
+}
@@ -1,6 +1,18 @@
 #[cfg(test)]
 mod tests;
 
+pub mod ass;
+pub mod hls;
+mod import;
+mod parser;
+mod settings;
+pub mod srt;
+pub mod ttml;
 mod webvtt;
+mod writer;
 
-pub use webvtt::{WebVTTCue, WebVTTFile};
+pub use import::{SubtitleImportError, SubtitleImportErrorKind};
+pub use parser::{WebVTTParseError, WebVTTParseErrorKind};
+pub use settings::{Align, CueSettings, LineAlign, LinePosition, PositionAlign, Region, Vertical};
+pub use webvtt::{escape, WebVTTBlock, WebVTTCue, WebVTTError, WebVTTFile};
+pub use writer::WebVTTWriter;
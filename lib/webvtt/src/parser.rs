@@ -0,0 +1,273 @@
+use thiserror::Error;
+
+use media_time::MediaTime;
+
+use crate::settings::{CueSettings, Region};
+use crate::webvtt::{WebVTTBlock, WebVTTCue, WebVTTFile};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("Invalid WebVTT at line {line}, column {column}: {kind}")]
+pub struct WebVTTParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: WebVTTParseErrorKind,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WebVTTParseErrorKind {
+    #[error("missing WEBVTT signature")]
+    MissingSignature,
+    #[error("missing cue timings")]
+    MissingTimings,
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+    #[error("expected \"-->\"")]
+    MissingArrow,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn error(&self, position: usize, kind: WebVTTParseErrorKind) -> WebVTTParseError {
+        WebVTTParseError {
+            line: self.number,
+            column: self.column(position),
+            kind,
+        }
+    }
+
+    pub fn column(&self, position: usize) -> usize {
+        self.text[..position].chars().count() + 1
+    }
+}
+
+/// Splits on `\n`, `\r\n` and `\r`, numbering lines from 1
+pub(crate) fn split_lines(input: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut rest = input;
+    while let Some(position) = rest.find(['\r', '\n']) {
+        lines.push(Line {
+            number: lines.len() + 1,
+            text: &rest[..position],
+        });
+        let separator = if rest[position..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        rest = &rest[position + separator..];
+    }
+    if !rest.is_empty() {
+        lines.push(Line {
+            number: lines.len() + 1,
+            text: rest,
+        });
+    }
+    lines
+}
+
+pub(crate) fn strip_bom(input: &str) -> &str {
+    input.strip_prefix('\u{feff}').unwrap_or(input)
+}
+
+pub(crate) fn parse(input: &str) -> Result<WebVTTFile, WebVTTParseError> {
+    let lines = split_lines(strip_bom(input));
+
+    let signature = lines.first().copied().unwrap_or(Line {
+        number: 1,
+        text: "",
+    });
+    let description = match signature.text.strip_prefix("WEBVTT") {
+        Some("") => None,
+        Some(rest) if rest.starts_with(' ') || rest.starts_with('\t') => {
+            Some(rest[1..].to_string())
+        }
+        _ => return Err(signature.error(0, WebVTTParseErrorKind::MissingSignature)),
+    };
+
+    let mut file = WebVTTFile::new();
+    file.set_description(description);
+
+    let mut index = 1;
+    // Header lines end at the first blank line, or at a cue that isn't separated by one
+    while let Some(line) = lines.get(index) {
+        if line.text.is_empty() || line.text.contains("-->") {
+            break;
+        }
+        file.add_header(line.text.to_string());
+        index += 1;
+    }
+
+    loop {
+        while lines.get(index).is_some_and(|line| line.text.is_empty()) {
+            index += 1;
+        }
+        let start = index;
+        while lines.get(index).is_some_and(|line| !line.text.is_empty()) {
+            index += 1;
+        }
+        if start == index {
+            break;
+        }
+        file.add_block(parse_block(&lines[start..index])?);
+    }
+
+    Ok(file)
+}
+
+fn is_keyword(text: &str, keyword: &str) -> bool {
+    match text.strip_prefix(keyword) {
+        Some(rest) => rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t'),
+        None => false,
+    }
+}
+
+fn join(lines: &[Line]) -> String {
+    lines
+        .iter()
+        .map(|line| line.text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_block(block: &[Line]) -> Result<WebVTTBlock, WebVTTParseError> {
+    let first = block[0];
+
+    if is_keyword(first.text, "NOTE") {
+        let mut text = Vec::new();
+        let rest = &first.text["NOTE".len()..];
+        if rest.len() > 1 {
+            text.push(&rest[1..]);
+        }
+        text.extend(block[1..].iter().map(|line| line.text));
+        return Ok(WebVTTBlock::Note(text.join("\n")));
+    }
+    if first.text.trim_end() == "STYLE" {
+        return Ok(WebVTTBlock::Style(join(&block[1..])));
+    }
+    if first.text.trim_end() == "REGION" {
+        return Ok(WebVTTBlock::Region(Region::parse(&join(&block[1..]))));
+    }
+
+    let (identifier, timings, payload) = if first.text.contains("-->") {
+        (None, first, &block[1..])
+    } else {
+        match block.get(1) {
+            Some(timings) => (Some(first.text.to_string()), *timings, &block[2..]),
+            None => {
+                return Err(WebVTTParseError {
+                    line: first.number + 1,
+                    column: 1,
+                    kind: WebVTTParseErrorKind::MissingTimings,
+                })
+            }
+        }
+    };
+    let cue = parse_timings(timings, join(payload))?.with_identifier(identifier);
+
+    Ok(WebVTTBlock::Cue(cue))
+}
+
+pub(crate) fn skip_whitespace(text: &str, position: usize) -> usize {
+    position + (text.len() - position - text[position..].trim_start().len())
+}
+
+fn parse_timings(line: Line, payload: String) -> Result<WebVTTCue, WebVTTParseError> {
+    let text = line.text;
+
+    let position = skip_whitespace(text, 0);
+    let (start, start_hours, position) = parse_timestamp(text, position)
+        .ok_or_else(|| line.error(position, WebVTTParseErrorKind::InvalidTimestamp))?;
+
+    let position = skip_whitespace(text, position);
+    if !text[position..].starts_with("-->") {
+        return Err(line.error(position, WebVTTParseErrorKind::MissingArrow));
+    }
+
+    let position = skip_whitespace(text, position + "-->".len());
+    let (end, end_hours, position) = parse_timestamp(text, position)
+        .filter(|(_, _, end)| {
+            // Settings have to be separated from the timestamp
+            text[*end..].is_empty() || text[*end..].starts_with([' ', '\t'])
+        })
+        .ok_or_else(|| line.error(position, WebVTTParseErrorKind::InvalidTimestamp))?;
+
+    let settings = text[position..].trim();
+
+    Ok(WebVTTCue::new(start, end, payload)
+        .with_settings(CueSettings::parse(settings))
+        .with_source(start_hours, end_hours, settings))
+}
+
+/// Parses `[hh:]mm:ss.ttt` at `position`, hours may have more than two digits. Returns the
+/// number of hour digits along with the time, so the timestamp can be written the same way.
+fn parse_timestamp(text: &str, position: usize) -> Option<(MediaTime, Option<usize>, usize)> {
+    let digits = |from: usize| -> Option<(i64, usize)> {
+        let length = text[from..]
+            .bytes()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        let value = text[from..from + length].parse().ok()?;
+        Some((value, from + length))
+    };
+    let expect = |at: usize, separator: u8| -> Option<usize> {
+        if text.as_bytes().get(at) == Some(&separator) {
+            Some(at + 1)
+        } else {
+            None
+        }
+    };
+
+    let (first, next) = digits(position)?;
+    let first_length = next - position;
+    let (second, after_second) = digits(expect(next, b':')?)?;
+    if after_second - next != 3 {
+        return None;
+    }
+
+    let (hours, hour_digits, minutes, seconds, next) = match expect(after_second, b':') {
+        Some(next) => {
+            let (seconds, after_seconds) = digits(next)?;
+            if after_seconds - next != 2 {
+                return None;
+            }
+            (first, Some(first_length), second, seconds, after_seconds)
+        }
+        // Without hours, the first component has to be valid minutes
+        None if first_length == 2 && first < 60 => (0, None, first, second, after_second),
+        None => return None,
+    };
+
+    let fraction = expect(next, b'.')?;
+    let (millis, end) = digits(fraction)?;
+    if end - fraction != 3 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    Some((
+        timestamp_millis(hours, minutes, seconds, millis)?,
+        hour_digits,
+        end,
+    ))
+}
+
+/// Hours are unbounded, so timestamps in untrusted input may not fit
+pub(crate) fn timestamp_millis(
+    hours: i64,
+    minutes: i64,
+    seconds: i64,
+    millis: i64,
+) -> Option<MediaTime> {
+    let millis = hours
+        .checked_mul(60)?
+        .checked_add(minutes)?
+        .checked_mul(60)?
+        .checked_add(seconds)?
+        .checked_mul(1000)?
+        .checked_add(millis)?;
+    Some(MediaTime::from_millis(millis))
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Vertical {
+    /// `rl`, lines grow to the left
+    RightToLeft,
+    /// `lr`, lines grow to the right
+    LeftToRight,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LinePosition {
+    /// Line number, negative numbers count from the end of the video
+    Number(f64),
+    Percentage(f64),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineAlign {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PositionAlign {
+    LineLeft,
+    Center,
+    LineRight,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+/// Cue settings following the timings, settings that are unknown or invalid are left out,
+/// parsed cues keep the original text to write it back unchanged
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSettings {
+    pub vertical: Option<Vertical>,
+    pub line: Option<(LinePosition, Option<LineAlign>)>,
+    /// Position in percent of the video width
+    pub position: Option<(f64, Option<PositionAlign>)>,
+    /// Size in percent of the video width
+    pub size: Option<f64>,
+    pub align: Option<Align>,
+    /// Identifier of the region the cue is shown in
+    pub region: Option<String>,
+}
+
+/// Region definition of a REGION block, percentages are relative to the region or the video.
+/// Parsed regions keep the original text and write it back while the fields are unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct Region {
+    pub id: String,
+    pub width: Option<f64>,
+    pub lines: Option<u32>,
+    pub region_anchor: Option<(f64, f64)>,
+    pub viewport_anchor: Option<(f64, f64)>,
+    pub scroll: bool,
+    text: Option<String>,
+}
+
+impl CueSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == CueSettings::default()
+    }
+
+    pub(crate) fn parse(text: &str) -> CueSettings {
+        let mut settings = CueSettings::default();
+        for (name, value) in split_settings(text) {
+            match name {
+                "vertical" => {
+                    settings.vertical = match value {
+                        "rl" => Some(Vertical::RightToLeft),
+                        "lr" => Some(Vertical::LeftToRight),
+                        _ => settings.vertical,
+                    }
+                }
+                "line" => {
+                    let (position, align) = split_align(value);
+                    let position = match position.strip_suffix('%') {
+                        Some(percentage) => {
+                            parse_percentage(percentage).map(LinePosition::Percentage)
+                        }
+                        None => parse_number(position).map(LinePosition::Number),
+                    };
+                    let align = match align {
+                        None => Some(None),
+                        Some("start") => Some(Some(LineAlign::Start)),
+                        Some("center") => Some(Some(LineAlign::Center)),
+                        Some("end") => Some(Some(LineAlign::End)),
+                        Some(_) => None,
+                    };
+                    if let (Some(position), Some(align)) = (position, align) {
+                        settings.line = Some((position, align));
+                    }
+                }
+                "position" => {
+                    let (position, align) = split_align(value);
+                    let align = match align {
+                        None => Some(None),
+                        Some("line-left") => Some(Some(PositionAlign::LineLeft)),
+                        Some("center") => Some(Some(PositionAlign::Center)),
+                        Some("line-right") => Some(Some(PositionAlign::LineRight)),
+                        Some(_) => None,
+                    };
+                    if let (Some(position), Some(align)) = (percentage(position), align) {
+                        settings.position = Some((position, align));
+                    }
+                }
+                "size" => settings.size = percentage(value).or(settings.size),
+                "align" => {
+                    settings.align = match value {
+                        "start" => Some(Align::Start),
+                        "center" => Some(Align::Center),
+                        "end" => Some(Align::End),
+                        "left" => Some(Align::Left),
+                        "right" => Some(Align::Right),
+                        _ => settings.align,
+                    }
+                }
+                "region" if !value.contains("-->") => settings.region = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+impl fmt::Display for CueSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut settings = Vec::new();
+        if let Some(vertical) = self.vertical {
+            settings.push(format!(
+                "vertical:{}",
+                match vertical {
+                    Vertical::RightToLeft => "rl",
+                    Vertical::LeftToRight => "lr",
+                }
+            ));
+        }
+        if let Some((position, align)) = self.line {
+            let position = match position {
+                LinePosition::Number(number) => number.to_string(),
+                LinePosition::Percentage(percentage) => format!("{}%", percentage),
+            };
+            settings.push(match align {
+                Some(align) => format!(
+                    "line:{},{}",
+                    position,
+                    match align {
+                        LineAlign::Start => "start",
+                        LineAlign::Center => "center",
+                        LineAlign::End => "end",
+                    }
+                ),
+                None => format!("line:{}", position),
+            });
+        }
+        if let Some((position, align)) = self.position {
+            settings.push(match align {
+                Some(align) => format!(
+                    "position:{}%,{}",
+                    position,
+                    match align {
+                        PositionAlign::LineLeft => "line-left",
+                        PositionAlign::Center => "center",
+                        PositionAlign::LineRight => "line-right",
+                    }
+                ),
+                None => format!("position:{}%", position),
+            });
+        }
+        if let Some(size) = self.size {
+            settings.push(format!("size:{}%", size));
+        }
+        if let Some(align) = self.align {
+            settings.push(format!(
+                "align:{}",
+                match align {
+                    Align::Start => "start",
+                    Align::Center => "center",
+                    Align::End => "end",
+                    Align::Left => "left",
+                    Align::Right => "right",
+                }
+            ));
+        }
+        if let Some(region) = &self.region {
+            settings.push(format!("region:{}", region));
+        }
+        write!(f, "{}", settings.join(" "))
+    }
+}
+
+/// Compares the settings, regardless of how they were written
+impl PartialEq for Region {
+    fn eq(&self, other: &Region) -> bool {
+        self.id == other.id
+            && self.width == other.width
+            && self.lines == other.lines
+            && self.region_anchor == other.region_anchor
+            && self.viewport_anchor == other.viewport_anchor
+            && self.scroll == other.scroll
+    }
+}
+
+impl Region {
+    pub fn new(id: String) -> Region {
+        Region {
+            id,
+            ..Region::default()
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Region {
+        let mut region = Region::default();
+        for (name, value) in split_settings(text) {
+            match name {
+                "id" if !value.contains("-->") => region.id = value.to_string(),
+                "width" => region.width = percentage(value).or(region.width),
+                "lines" if value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                    region.lines = value.parse().ok().or(region.lines)
+                }
+                "regionanchor" => region.region_anchor = anchor(value).or(region.region_anchor),
+                "viewportanchor" => {
+                    region.viewport_anchor = anchor(value).or(region.viewport_anchor)
+                }
+                "scroll" => region.scroll = value == "up",
+                _ => {}
+            }
+        }
+        region.text = Some(text.to_string());
+        region
+    }
+}
+
+/// Writes the parsed text if the fields still match it, or one setting per line
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(text) = &self.text {
+            if Region::parse(text) == *self {
+                return f.write_str(text);
+            }
+        }
+        let mut settings = Vec::new();
+        if !self.id.is_empty() {
+            settings.push(format!("id:{}", self.id));
+        }
+        if let Some(width) = self.width {
+            settings.push(format!("width:{}%", width));
+        }
+        if let Some(lines) = self.lines {
+            settings.push(format!("lines:{}", lines));
+        }
+        if let Some((x, y)) = self.region_anchor {
+            settings.push(format!("regionanchor:{}%,{}%", x, y));
+        }
+        if let Some((x, y)) = self.viewport_anchor {
+            settings.push(format!("viewportanchor:{}%,{}%", x, y));
+        }
+        if self.scroll {
+            settings.push("scroll:up".to_string());
+        }
+        write!(f, "{}", settings.join("\n"))
+    }
+}
+
+/// Splits whitespace separated `name:value` pairs, skipping those without name or value
+fn split_settings(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.split_whitespace().filter_map(|setting| {
+        let position = setting.find(':')?;
+        let (name, value) = (&setting[..position], &setting[position + 1..]);
+        if name.is_empty() || value.is_empty() {
+            None
+        } else {
+            Some((name, value))
+        }
+    })
+}
+
+fn split_align(value: &str) -> (&str, Option<&str>) {
+    match value.find(',') {
+        Some(position) => (&value[..position], Some(&value[position + 1..])),
+        None => (value, None),
+    }
+}
+
+/// Parses `[-]digits[.digits]`, rejecting the other formats `f64` accepts
+fn parse_number(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let mut parts = digits.splitn(2, '.');
+    let valid = parts.all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+    if valid {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse_percentage(text: &str) -> Option<f64> {
+    if text.starts_with('-') {
+        return None;
+    }
+    parse_number(text).filter(|value| *value <= 100.0)
+}
+
+fn percentage(value: &str) -> Option<f64> {
+    parse_percentage(value.strip_suffix('%')?)
+}
+
+fn anchor(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = split_align(value);
+    Some((percentage(x)?, percentage(y?)?))
+}
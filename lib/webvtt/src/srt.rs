@@ -0,0 +1,246 @@
+//! SubRip import and export, formatting tags other than bold, italic and underline are dropped
+
+use std::io::Write;
+
+use media_time::MediaTime;
+
+use crate::ass;
+use crate::parser::{skip_whitespace, split_lines, strip_bom, timestamp_millis, Line};
+use crate::settings::CueSettings;
+use crate::webvtt::{WebVTTCue, WebVTTError, WebVTTFile};
+use crate::{SubtitleImportError, SubtitleImportErrorKind};
+
+pub fn parse(input: &str) -> Result<WebVTTFile, SubtitleImportError> {
+    let lines = split_lines(strip_bom(input));
+    let mut file = WebVTTFile::new();
+
+    let mut index = 0;
+    loop {
+        // Blank lines often contain stray whitespace
+        while lines
+            .get(index)
+            .is_some_and(|line| line.text.trim().is_empty())
+        {
+            index += 1;
+        }
+        let start = index;
+        while lines
+            .get(index)
+            .is_some_and(|line| !line.text.trim().is_empty())
+        {
+            index += 1;
+        }
+        if start == index {
+            break;
+        }
+        if let Some(cue) = parse_block(&lines[start..index])? {
+            file.add(cue);
+        }
+    }
+
+    Ok(file)
+}
+
+fn parse_block(block: &[Line]) -> Result<Option<WebVTTCue>, SubtitleImportError> {
+    // The counter is redundant, so files without one are accepted as well
+    let (timings, payload) = if block[0].text.contains("-->") {
+        (block[0], &block[1..])
+    } else {
+        match block.get(1) {
+            Some(timings) => (*timings, &block[2..]),
+            None => {
+                return Err(SubtitleImportError {
+                    line: block[0].number + 1,
+                    column: 1,
+                    kind: SubtitleImportErrorKind::MissingTimings,
+                })
+            }
+        }
+    };
+
+    let text = timings.text;
+    let position = skip_whitespace(text, 0);
+    let (start, position) = parse_timestamp(text, position)
+        .ok_or_else(|| timings.import_error(position, SubtitleImportErrorKind::InvalidTimestamp))?;
+    let position = skip_whitespace(text, position);
+    if !text[position..].starts_with("-->") {
+        return Err(timings.import_error(position, SubtitleImportErrorKind::MissingArrow));
+    }
+    let position = skip_whitespace(text, position + "-->".len());
+    // Anything following the end, like the `X1:` coordinates of some files, is ignored
+    let (end, _) = parse_timestamp(text, position)
+        .ok_or_else(|| timings.import_error(position, SubtitleImportErrorKind::InvalidTimestamp))?;
+
+    let lines = payload
+        .iter()
+        .map(|line| line.text.trim())
+        .collect::<Vec<_>>();
+    let (payload, settings) = convert_text(&lines.join("\n"));
+    if payload.is_empty() || end <= start {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        WebVTTCue::new(start, end, payload).with_settings(settings),
+    ))
+}
+
+/// Parses `hh:mm:ss,ttt` at `position`, also accepting a `.` as decimal separator
+fn parse_timestamp(text: &str, position: usize) -> Option<(MediaTime, usize)> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| -> Option<(i64, usize)> {
+        let length = bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        let value = text[from..from + length].parse().ok()?;
+        Some((value, from + length))
+    };
+    let expect = |at: usize, separators: &[u8]| -> Option<usize> {
+        match bytes.get(at) {
+            Some(byte) if separators.contains(byte) => Some(at + 1),
+            _ => None,
+        }
+    };
+
+    let (hours, next) = digits(position)?;
+    let (minutes, next) = digits(expect(next, b":")?)?;
+    let (seconds, next) = digits(expect(next, b":")?)?;
+    let fraction = expect(next, b",.")?;
+    let (millis, end) = digits(fraction)?;
+    if end - fraction != 3 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    Some((timestamp_millis(hours, minutes, seconds, millis)?, end))
+}
+
+/// Converts SubRip text, which uses HTML-like tags and sometimes ASS alignment overrides
+fn convert_text(text: &str) -> (String, CueSettings) {
+    let mut result = String::new();
+    let mut settings = CueSettings::default();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            // A tag has to start with its name, so text like `1 < 2` is kept
+            '<' => match rest.find('>').filter(|&end| is_tag(&rest[..end])) {
+                Some(end) => {
+                    let tag = rest[..end].trim().to_ascii_lowercase();
+                    if let "b" | "i" | "u" | "/b" | "/i" | "/u" = tag.as_str() {
+                        result.push_str(&format!("<{}>", tag));
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => result.push_str("&lt;"),
+            },
+            '{' if rest.starts_with('\\') && rest.contains('}') => {
+                let end = rest.find('}').unwrap_or(0);
+                let (_, alignment) = ass::convert_text(&format!("{{{}}}", &rest[..end]));
+                if !alignment.is_empty() {
+                    settings = alignment;
+                }
+                rest = &rest[end + 1..];
+            }
+            '&' => result.push_str("&amp;"),
+            '>' => result.push_str("&gt;"),
+            c => result.push(c),
+        }
+    }
+
+    let lines = result
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    (lines.join("\n"), settings)
+}
+
+fn is_tag(tag: &str) -> bool {
+    let name = tag.strip_prefix('/').unwrap_or(tag);
+    name.starts_with(|c: char| c.is_ascii_alphabetic()) && !name.contains('<')
+}
+
+pub fn write_to(file: &WebVTTFile, writer: &mut impl Write) -> Result<(), WebVTTError> {
+    writer
+        .write_all(to_string(file).as_bytes())
+        .map_err(WebVTTError::WriteError)
+}
+
+pub fn to_string(file: &WebVTTFile) -> String {
+    let mut output = String::new();
+    for (index, cue) in file.cues().enumerate() {
+        output.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            timestamp(cue.start()),
+            timestamp(cue.end()),
+            payload_text(cue.payload())
+        ));
+    }
+    output
+}
+
+fn timestamp(time: MediaTime) -> String {
+    let millis = time.milliseconds().max(0);
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Converts a WebVTT payload to SubRip text, keeping bold, italic and underline
+fn payload_text(payload: &str) -> String {
+    let mut result = String::new();
+    let mut rest = payload;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '<' => {
+                let end = rest.find('>').unwrap_or(rest.len());
+                let tag = &rest[..end];
+                let (closing, tag) = match tag.strip_prefix('/') {
+                    Some(tag) => ("/", tag),
+                    None => ("", tag),
+                };
+                // Classes and annotations, as in `<i.loud>`, have no equivalent
+                let name = tag
+                    .split(|c: char| c == '.' || c.is_whitespace())
+                    .next()
+                    .unwrap_or("");
+                if let "b" | "i" | "u" = name {
+                    result.push_str(&format!("<{}{}>", closing, name));
+                }
+                rest = rest.get(end + 1..).unwrap_or("");
+            }
+            '&' => {
+                let entities = [
+                    ("amp;", "&"),
+                    ("lt;", "<"),
+                    ("gt;", ">"),
+                    ("nbsp;", "\u{a0}"),
+                    ("lrm;", "\u{200e}"),
+                    ("rlm;", "\u{200f}"),
+                ];
+                match entities.iter().find(|(entity, _)| rest.starts_with(entity)) {
+                    Some((entity, replacement)) => {
+                        result.push_str(replacement);
+                        rest = &rest[entity.len()..];
+                    }
+                    None => result.push('&'),
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    // Blank lines would end the subtitle early
+    result
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
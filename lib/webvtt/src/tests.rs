@@ -1,4 +1,650 @@
+use media_time::MediaTime;
+
+use crate::{
+    ass, escape, hls, srt, ttml, Align, CueSettings, LineAlign, LinePosition, PositionAlign,
+    Region, SubtitleImportError, SubtitleImportErrorKind, Vertical, WebVTTBlock, WebVTTCue,
+    WebVTTFile, WebVTTParseError, WebVTTParseErrorKind, WebVTTWriter,
+};
+
 #[test]
 fn it_works() {
     assert_eq!(2 + 2, 4);
 }
+
+fn serialize(file: &WebVTTFile) -> String {
+    let mut output = Vec::new();
+    file.write_to(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn parse_error(input: &str) -> WebVTTParseError {
+    match WebVTTFile::parse(input) {
+        Ok(_) => panic!("parsing {:?} should fail", input),
+        Err(error) => error,
+    }
+}
+
+const DOCUMENT: &str = "WEBVTT - Sample
+Kind: captions
+Language: en
+
+STYLE
+::cue {
+  color: yellow;
+}
+
+REGION
+id:fred
+width:40%
+lines:3
+regionanchor:0%,100%
+viewportanchor:10%,90%
+scroll:up
+
+NOTE This is a comment
+
+NOTE
+Spanning
+several lines
+
+intro
+00:01.000 --> 00:04.500 line:0 position:10%,line-left size:80% align:start region:fred
+Never drink liquid nitrogen.
+
+01:02:03.004 --> 01:02:05.000
+<v Roger>It will perforate
+your stomach.</v>
+
+";
+
+#[test]
+fn parse_works() {
+    let file = WebVTTFile::parse(DOCUMENT).unwrap();
+    assert_eq!(file.description(), Some("- Sample"));
+    assert_eq!(file.header(), &["Kind: captions", "Language: en"]);
+
+    let blocks = file.blocks();
+    assert_eq!(blocks.len(), 6);
+    match &blocks[0] {
+        WebVTTBlock::Style(style) => assert_eq!(style, "::cue {\n  color: yellow;\n}"),
+        _ => panic!("expected a style block"),
+    }
+    match &blocks[1] {
+        WebVTTBlock::Region(region) => {
+            let mut expected = Region::new("fred".to_string());
+            expected.width = Some(40.0);
+            expected.lines = Some(3);
+            expected.region_anchor = Some((0.0, 100.0));
+            expected.viewport_anchor = Some((10.0, 90.0));
+            expected.scroll = true;
+            assert_eq!(region, &expected);
+        }
+        _ => panic!("expected a region block"),
+    }
+    match &blocks[2] {
+        WebVTTBlock::Note(note) => assert_eq!(note, "This is a comment"),
+        _ => panic!("expected a note"),
+    }
+    match &blocks[3] {
+        WebVTTBlock::Note(note) => assert_eq!(note, "Spanning\nseveral lines"),
+        _ => panic!("expected a note"),
+    }
+
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].identifier(), Some("intro"));
+    assert_eq!(cues[0].start(), MediaTime::from_millis(1000));
+    assert_eq!(cues[0].end(), MediaTime::from_millis(4500));
+    assert_eq!(
+        cues[0].settings(),
+        &CueSettings {
+            vertical: None,
+            line: Some((LinePosition::Number(0.0), None)),
+            position: Some((10.0, Some(PositionAlign::LineLeft))),
+            size: Some(80.0),
+            align: Some(Align::Start),
+            region: Some("fred".to_string()),
+        }
+    );
+    assert_eq!(cues[0].payload(), "Never drink liquid nitrogen.");
+    assert_eq!(cues[1].identifier(), None);
+    assert_eq!(cues[1].start(), MediaTime::from_millis(3_723_004));
+    assert!(cues[1].settings().is_empty());
+    assert_eq!(
+        cues[1].payload(),
+        "<v Roger>It will perforate\nyour stomach.</v>"
+    );
+}
+
+#[test]
+fn round_trip_is_byte_stable() {
+    let file = WebVTTFile::parse(DOCUMENT).unwrap();
+    assert_eq!(serialize(&file), DOCUMENT);
+
+    let mut written = WebVTTFile::new();
+    written.add(WebVTTCue::new(
+        MediaTime::from_millis(0),
+        MediaTime::from_millis(2000),
+        "preview.jpg#xywh=0,0,240,135".to_string(),
+    ));
+    let serialized = serialize(&written);
+    assert_eq!(
+        serialize(&WebVTTFile::parse(&serialized).unwrap()),
+        serialized
+    );
+
+    let document = "WEBVTT
+
+00:00:01.000 --> 00:02.500 align:start line:0% unknown:setting size:120%
+first
+
+100:00:00.000 --> 0100:00:01.000 region:fred  vertical:rl
+second
+
+";
+    let file = WebVTTFile::parse(document).unwrap();
+    assert_eq!(serialize(&file), document);
+
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(cues[0].settings().align, Some(Align::Start));
+    assert_eq!(cues[0].settings().size, None);
+    assert_eq!(cues[1].start(), MediaTime::from_seconds(360_000));
+
+    // Replaced settings are written in canonical form, the timestamps keep their form
+    let cue = cues[0].clone().with_settings(cues[0].settings().clone());
+    assert_eq!(
+        cue.to_string(),
+        "00:00:01.000 --> 00:02.500 line:0% align:start\nfirst"
+    );
+
+    let document = "WEBVTT
+
+REGION
+id:fred width:40.0% lines:3
+
+00:01.000 --> 00:02.000 region:fred
+a
+ 
+b
+
+";
+    let file = WebVTTFile::parse(document).unwrap();
+    assert_eq!(serialize(&file), document);
+    assert_eq!(file.cues().next().unwrap().payload(), "a\n \nb");
+
+    // Changed regions are written in canonical form
+    let mut region = match &file.blocks()[0] {
+        WebVTTBlock::Region(region) => region.clone(),
+        _ => panic!("expected a region block"),
+    };
+    assert_eq!(region.width, Some(40.0));
+    region.width = Some(50.0);
+    assert_eq!(region.to_string(), "id:fred\nwidth:50%\nlines:3");
+}
+
+#[test]
+fn negative_times_are_written_as_zero() {
+    let mut file = WebVTTFile::new();
+    file.add(WebVTTCue::new(
+        MediaTime::from_millis(-500),
+        MediaTime::from_millis(1500),
+        "before the start".to_string(),
+    ));
+    file.add(WebVTTCue::new(
+        MediaTime::from_seconds(-2),
+        MediaTime::from_seconds(-1),
+        "entirely before the start".to_string(),
+    ));
+    let serialized = serialize(&file);
+    assert_eq!(
+        serialized,
+        "WEBVTT\n\n00:00.000 --> 00:01.500\nbefore the start\n\n00:00.000 --> 00:00.000\nentirely before the start\n\n"
+    );
+    assert_eq!(
+        serialize(&WebVTTFile::parse(&serialized).unwrap()),
+        serialized
+    );
+}
+
+#[test]
+fn parse_normalizes_input() {
+    let file = WebVTTFile::parse(
+        "\u{feff}WEBVTT\r\n\r\n\r\n00:00:01.000-->00:00:02.000\r\nfirst\r\n\r\n2\n00:02.000 --> 00:03.000\nsecond",
+    )
+    .unwrap();
+    assert_eq!(
+        serialize(&file),
+        "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nfirst\n\n2\n00:02.000 --> 00:03.000\nsecond\n\n"
+    );
+}
+
+#[test]
+fn parse_reports_positions() {
+    assert_eq!(
+        parse_error("WEBVTTX\n"),
+        WebVTTParseError {
+            line: 1,
+            column: 1,
+            kind: WebVTTParseErrorKind::MissingSignature,
+        }
+    );
+    assert_eq!(
+        parse_error("WEBVTT\n\n00:01.000 --> 00:02.000\nok\n\nsecond\n00:01.000 -> 00:02.000\n"),
+        WebVTTParseError {
+            line: 7,
+            column: 11,
+            kind: WebVTTParseErrorKind::MissingArrow,
+        }
+    );
+    assert_eq!(
+        parse_error("WEBVTT\n\nid\n00:01.000 --> 00:61.000\n"),
+        WebVTTParseError {
+            line: 4,
+            column: 15,
+            kind: WebVTTParseErrorKind::InvalidTimestamp,
+        }
+    );
+    assert_eq!(
+        parse_error("WEBVTT\n\n9999999999999999:00:00.000 --> 00:01.000\n"),
+        WebVTTParseError {
+            line: 3,
+            column: 1,
+            kind: WebVTTParseErrorKind::InvalidTimestamp,
+        }
+    );
+    assert_eq!(
+        parse_error("WEBVTT\n\njust an identifier\n"),
+        WebVTTParseError {
+            line: 4,
+            column: 1,
+            kind: WebVTTParseErrorKind::MissingTimings,
+        }
+    );
+}
+
+#[test]
+fn from_reader_works() {
+    let file = WebVTTFile::from_reader(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(file.cues().count(), 2);
+}
+
+#[test]
+fn settings_work() {
+    let file = WebVTTFile::parse(
+        "WEBVTT\n\n00:01.000 --> 00:02.000 vertical:lr line:-2,end align:middle size:120% foo:bar\nfirst\n\n00:01.000 --> 00:02.000 line:12.5%,center position:50%\nsecond\n",
+    )
+    .unwrap();
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(
+        cues[0].settings(),
+        &CueSettings {
+            vertical: Some(Vertical::LeftToRight),
+            line: Some((LinePosition::Number(-2.0), Some(LineAlign::End))),
+            ..CueSettings::default()
+        }
+    );
+    assert_eq!(cues[0].settings().to_string(), "vertical:lr line:-2,end");
+    assert_eq!(
+        cues[1].settings().to_string(),
+        "line:12.5%,center position:50%"
+    );
+}
+
+#[test]
+fn escaping_works() {
+    let mut file = WebVTTFile::new();
+    file.set_description(Some("Broken\n--> header".to_string()));
+    file.add_block(WebVTTBlock::Note("one\n\n--> two".to_string()));
+    file.add(
+        WebVTTCue::new(
+            MediaTime::from_millis(0),
+            MediaTime::from_millis(1000),
+            format!(
+                "<i>{}</i>\n\n{}",
+                escape("Tom & Jerry"),
+                escape("a --> <b>")
+            ),
+        )
+        .with_identifier(Some("id -->\n".to_string())),
+    );
+    let serialized = serialize(&file);
+    assert_eq!(
+        serialized,
+        "WEBVTT Broken --&gt; header\n\nNOTE\none\n--&gt; two\n\nid --&gt; \n00:00.000 --> 00:01.000\n<i>Tom &amp; Jerry</i>\na --&gt; &lt;b&gt;\n\n"
+    );
+
+    let parsed = WebVTTFile::parse(&serialized).unwrap();
+    assert_eq!(parsed.blocks().len(), 2);
+    assert_eq!(serialize(&parsed), serialized);
+}
+
+#[test]
+fn display_matches_written_file() {
+    let file = WebVTTFile::parse(DOCUMENT).unwrap();
+    assert_eq!(file.to_string(), DOCUMENT);
+    assert_eq!(
+        file.cues().next().unwrap().to_string(),
+        "intro\n00:01.000 --> 00:04.500 line:0 position:10%,line-left size:80% align:start region:fred\nNever drink liquid nitrogen."
+    );
+}
+
+#[test]
+fn writer_streams_blocks() {
+    let file = WebVTTFile::parse(DOCUMENT).unwrap();
+    let writer = WebVTTWriter::with_header(Vec::new(), file.description(), file.header()).unwrap();
+    assert_eq!(
+        writer.into_inner(),
+        b"WEBVTT - Sample\nKind: captions\nLanguage: en\n\n"
+    );
+
+    let mut writer =
+        WebVTTWriter::with_header(Vec::new(), file.description(), file.header()).unwrap();
+    for block in file.blocks() {
+        writer.write_block(block).unwrap();
+    }
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), DOCUMENT);
+
+    let mut writer = WebVTTWriter::new(Vec::new()).unwrap();
+    writer
+        .write_cue(&WebVTTCue::new(
+            MediaTime::from_millis(0),
+            MediaTime::from_millis(2000),
+            "preview.jpg#xywh=0,0,240,135".to_string(),
+        ))
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        "WEBVTT\n\n00:00.000 --> 00:02.000\npreview.jpg#xywh=0,0,240,135\n\n"
+    );
+}
+
+#[test]
+fn srt_works() {
+    let file = srt::parse(
+        "1\r\n00:00:01,000 --> 00:00:02,500 X1:10 X2:20\r\n<font color=\"red\"><i>Hello</i></font> & 1 < 2\r\n\r\n  \r\n2\r\n00:00:03.000 --> 00:00:04,000\r\n{\\an8}<B>Top</B>\r\nline\r\n",
+    )
+    .unwrap();
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].start(), MediaTime::from_millis(1000));
+    assert_eq!(cues[0].end(), MediaTime::from_millis(2500));
+    assert_eq!(cues[0].payload(), "<i>Hello</i> &amp; 1 &lt; 2");
+    assert_eq!(cues[1].payload(), "<b>Top</b>\nline");
+    assert_eq!(cues[1].settings().to_string(), "line:0");
+
+    assert_eq!(
+        srt::to_string(&file),
+        "1\n00:00:01,000 --> 00:00:02,500\n<i>Hello</i> & 1 < 2\n\n2\n00:00:03,000 --> 00:00:04,000\n<b>Top</b>\nline\n\n"
+    );
+    let exported = WebVTTFile::parse(
+        "WEBVTT\n\n01:02:03.004 --> 01:02:05.000\n<v Roger><i.loud>Hi</i></v>&nbsp;there\n",
+    )
+    .unwrap();
+    assert_eq!(
+        srt::to_string(&exported),
+        "1\n01:02:03,004 --> 01:02:05,000\n<i>Hi</i>\u{a0}there\n\n"
+    );
+
+    assert_eq!(
+        srt::parse("1\n00:00:01,000 -> 00:00:02,000\nbroken\n").unwrap_err(),
+        SubtitleImportError {
+            line: 2,
+            column: 14,
+            kind: SubtitleImportErrorKind::MissingArrow,
+        }
+    );
+    assert_eq!(
+        srt::parse("1\n9999999999999999:00:00,000 --> 00:00:02,000\noverflow\n").unwrap_err(),
+        SubtitleImportError {
+            line: 2,
+            column: 1,
+            kind: SubtitleImportErrorKind::InvalidTimestamp,
+        }
+    );
+}
+
+const ASS_DOCUMENT: &str = "[Script Info]
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, Bold, Italic, Alignment
+Style: Default,Arial,20,&H00FFFFFF,0,0,2
+Style: Sign Top,Arial,20,&H0000FFFF,-1,0,8
+Style: Unused,Arial,20,&H000000FF,0,1,2
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 1,0:00:05.00,0:00:06.00,Sign Top,,0,0,0,,EXIT
+Dialogue: 0,0:00:01.50,0:00:03.00,Default,,0,0,0,,{\\i1}Hello,{\\i0} world\\Nsecond <line>
+Comment: 0,0:00:02.00,0:00:03.00,Default,,0,0,0,,ignored
+Dialogue: 0,0:00:03.00,0:00:04.00,Missing,,0,0,0,,{\\an7}Fallback
+";
+
+#[test]
+fn ass_works() {
+    let file = ass::parse(ASS_DOCUMENT).unwrap();
+    match &file.blocks()[0] {
+        WebVTTBlock::Style(style) => assert_eq!(
+            style,
+            "::cue(.Default) {\n  color: #ffffff;\n}\n::cue(.Sign_Top) {\n  color: #ffff00;\n  font-weight: bold;\n}"
+        ),
+        _ => panic!("expected a style block"),
+    }
+
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(cues.len(), 3);
+    assert_eq!(cues[0].start(), MediaTime::from_millis(1500));
+    assert_eq!(
+        cues[0].payload(),
+        "<c.Default><i>Hello,</i> world\nsecond &lt;line&gt;</c>"
+    );
+    assert!(cues[0].settings().is_empty());
+    assert_eq!(cues[1].payload(), "<c.Default>Fallback</c>");
+    assert_eq!(cues[1].settings().to_string(), "line:0 align:left");
+    assert_eq!(cues[2].payload(), "<c.Sign_Top>EXIT</c>");
+    assert_eq!(cues[2].settings().to_string(), "line:0");
+
+    assert_eq!(
+        ass::parse("[Events]\nDialogue: 0,0:00:0x.00,0:00:02.00,Default,,0,0,0,,Text\n")
+            .unwrap_err(),
+        SubtitleImportError {
+            line: 2,
+            column: 13,
+            kind: SubtitleImportErrorKind::InvalidTimestamp,
+        }
+    );
+    assert_eq!(
+        ass::parse(
+            "[Events]\nDialogue: 0,9999999999999999:00:00.00,0:00:02.00,Default,,0,0,0,,Text\n"
+        )
+        .unwrap_err(),
+        SubtitleImportError {
+            line: 2,
+            column: 13,
+            kind: SubtitleImportErrorKind::InvalidTimestamp,
+        }
+    );
+    for timestamp in &["0:-5:00.00", "-1:00:00.00", "0:00:+5.00", "0:00:05.-1"] {
+        let document = format!(
+            "[Events]\nDialogue: 0,{},0:00:02.00,Default,,0,0,0,,Text\n",
+            timestamp
+        );
+        assert_eq!(
+            ass::parse(&document).unwrap_err().kind,
+            SubtitleImportErrorKind::InvalidTimestamp
+        );
+    }
+    assert_eq!(
+        ass::parse("1\n00:00:01,000 --> 00:00:02,000\nnot ass\n")
+            .unwrap_err()
+            .kind,
+        SubtitleImportErrorKind::MissingEvents
+    );
+}
+
+#[test]
+fn ttml_works() {
+    let file = ttml::parse(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+    xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:frameRate="25" ttp:tickRate="10000000">
+  <head>
+    <styling>
+      <style xml:id="emphasis" tts:fontStyle="italic"/>
+    </styling>
+  </head>
+  <body>
+    <!-- comment -->
+    <div begin="10s">
+      <p begin="00:00:01.000" end="00:00:02:12">Fish &amp;
+        chips<br/><span style="emphasis">for   two</span></p>
+      <p begin="30000000t" dur="500ms"><![CDATA[a < b]]></p>
+      <p begin="1s">no end</p>
+      <p begin="00:00:05:01" dur="0.0000005s">frame</p>
+    </div>
+  </body>
+</tt>
+"#,
+    )
+    .unwrap();
+    let cues = file.cues().collect::<Vec<_>>();
+    assert_eq!(cues.len(), 3);
+    assert_eq!(cues[0].start(), MediaTime::from_millis(11_000));
+    assert_eq!(cues[0].end(), MediaTime::from_millis(12_480));
+    assert_eq!(cues[0].payload(), "Fish &amp; chips\n<i>for two</i>");
+    assert_eq!(cues[1].start(), MediaTime::from_millis(13_000));
+    assert_eq!(cues[1].end(), MediaTime::from_millis(13_500));
+    assert_eq!(cues[1].payload(), "a &lt; b");
+    // Times keep sub-millisecond precision
+    assert_eq!(cues[2].start(), MediaTime::from_millis(15_040));
+    assert_eq!(cues[2].end(), MediaTime::from_nanos(15_040_000_500));
+    assert!(ttml::parse(
+        r#"<tt><body><p begin="99999999999999999999s" end="100000000000000000000s">x</p></body></tt>"#
+    )
+    .is_err());
+
+    assert_eq!(
+        ttml::parse("<tt>\n  <body><p></div></body></tt>").unwrap_err(),
+        SubtitleImportError {
+            line: 2,
+            column: 14,
+            kind: SubtitleImportErrorKind::InvalidXml,
+        }
+    );
+    assert_eq!(
+        ttml::parse("<html/>").unwrap_err().kind,
+        SubtitleImportErrorKind::MissingTtRoot
+    );
+
+    let nested = |depth: usize| {
+        format!(
+            "<tt><body><div><p begin=\"1s\" end=\"2s\">{}text{}</p></div></body></tt>",
+            "<span>".repeat(depth),
+            "</span>".repeat(depth)
+        )
+    };
+    assert!(ttml::parse(&nested(60)).is_ok());
+    let error = ttml::parse(&nested(100_000)).unwrap_err();
+    assert_eq!(error.kind, SubtitleImportErrorKind::InvalidXml);
+    // The 61st span is nested 64 levels deep
+    assert_eq!((error.line, error.column), (1, 39 + 6 * 60));
+}
+
+#[test]
+fn hls_segmenting_works() {
+    let file = WebVTTFile::parse(
+        "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:0,LOCAL:00:00:00.000\n\nSTYLE\n::cue { color: red }\n\nNOTE dropped\n\n00:01.000 --> 00:02.000\nfirst\n\n00:03.000 --> 00:05.000\nspanning\n",
+    )
+    .unwrap();
+
+    let segmenter = hls::HlsSegmenter::new(MediaTime::from_seconds(4))
+        .unwrap()
+        .with_mpegts_offset(900_000);
+    let segments = segmenter.segment(&file, MediaTime::from_millis(10_500));
+    assert_eq!(segments.len(), 3);
+    assert_eq!(segments[2].start, MediaTime::from_seconds(8));
+    assert_eq!(segments[2].duration, MediaTime::from_millis(2500));
+    assert_eq!(
+        segments[0].file.to_string(),
+        "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\nSTYLE\n::cue { color: red }\n\n00:01.000 --> 00:02.000\nfirst\n\n00:03.000 --> 00:05.000\nspanning\n\n"
+    );
+    let payloads = |segment: &hls::HlsSegment| {
+        segment
+            .file
+            .cues()
+            .map(|cue| cue.payload().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(payloads(&segments[0]), ["first", "spanning"]);
+    assert_eq!(payloads(&segments[1]), ["spanning"]);
+    assert!(payloads(&segments[2]).is_empty());
+
+    let uris = ["a-0.vtt", "a-1.vtt", "a-2.vtt"]
+        .iter()
+        .map(|uri| uri.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        segmenter.playlist(&segments, &uris),
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n#EXTINF:4.000,\na-0.vtt\n#EXTINF:4.000,\na-1.vtt\n#EXTINF:2.500,\na-2.vtt\n#EXT-X-ENDLIST\n"
+    );
+}
+
+#[test]
+fn hls_segments_cover_late_cues() {
+    let mut file = WebVTTFile::new();
+    file.add(WebVTTCue::new(
+        MediaTime::from_millis(9_000),
+        MediaTime::from_millis(13_000),
+        "crossing the end".to_string(),
+    ));
+    file.add(WebVTTCue::new(
+        MediaTime::from_millis(13_000),
+        MediaTime::from_millis(14_000),
+        "after the end".to_string(),
+    ));
+
+    let segmenter = hls::HlsSegmenter::new(MediaTime::from_seconds(4)).unwrap();
+    let segments = segmenter.segment(&file, MediaTime::from_millis(10_500));
+    let windows = segments
+        .iter()
+        .map(|segment| {
+            (
+                segment.start.milliseconds(),
+                segment.duration.milliseconds(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        windows,
+        [(0, 4000), (4000, 4000), (8000, 4000), (12_000, 2000)]
+    );
+    for segment in &segments {
+        for cue in segment.file.cues() {
+            // Every cue overlaps the time window of the segments containing it
+            assert!(cue.start() < segment.start + segment.duration);
+            assert!(cue.end() > segment.start);
+        }
+    }
+    assert_eq!(segments[2].file.cues().count(), 1);
+    assert_eq!(segments[3].file.cues().count(), 2);
+
+    // Without a known duration, the segments end with the last cue
+    let segments = segmenter.segment(&file, MediaTime::from_seconds(0));
+    assert_eq!(segments.len(), 4);
+    assert_eq!(segments[3].duration, MediaTime::from_seconds(2));
+}
+
+#[test]
+fn hls_target_duration_has_to_be_positive() {
+    for duration in &[
+        MediaTime::from_seconds(0),
+        MediaTime::from_seconds(-4),
+        MediaTime::from_nanos(500),
+    ] {
+        assert_eq!(
+            hls::HlsSegmenter::new(*duration).err(),
+            Some(hls::HlsError::TargetDurationTooShort(*duration))
+        );
+    }
+    assert!(hls::HlsSegmenter::new(MediaTime::from_millis(1)).is_ok());
+}
@@ -0,0 +1,516 @@
+//! TTML and DFXP import. Only timing, line breaks and bold, italic and underline styling
+//! of paragraphs and spans are kept, regions and other styles are dropped.
+
+use std::collections::HashMap;
+
+use media_time::MediaTime;
+
+use crate::parser::strip_bom;
+use crate::webvtt::{escape, WebVTTCue, WebVTTFile};
+use crate::{SubtitleImportError, SubtitleImportErrorKind};
+
+/// Element of the document, names are stored without namespace prefix
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    // Byte offset of the start tag
+    offset: usize,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Timing {
+    frame_rate: f64,
+    sub_frame_rate: f64,
+    tick_rate: f64,
+}
+
+#[derive(Copy, Clone, Default)]
+struct TextStyle {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+pub fn parse(input: &str) -> Result<WebVTTFile, SubtitleImportError> {
+    let input = strip_bom(input);
+    let root = XmlReader { input, position: 0 }
+        .document()
+        .map_err(|offset| error(input, offset, SubtitleImportErrorKind::InvalidXml))?;
+    if root.name != "tt" {
+        return Err(error(
+            input,
+            root.offset,
+            SubtitleImportErrorKind::MissingTtRoot,
+        ));
+    }
+
+    let number = |name: &str| {
+        root.attribute(name)
+            .and_then(|value| value.trim().parse().ok())
+    };
+    let mut frame_rate = number("frameRate").unwrap_or(30.0);
+    if let Some(multiplier) = root.attribute("frameRateMultiplier") {
+        let mut parts = multiplier.split_whitespace().map(str::parse::<f64>);
+        if let (Some(Ok(numerator)), Some(Ok(denominator))) = (parts.next(), parts.next()) {
+            if denominator > 0.0 {
+                frame_rate = frame_rate * numerator / denominator;
+            }
+        }
+    }
+    let sub_frame_rate = number("subFrameRate").unwrap_or(1.0);
+    let timing = Timing {
+        frame_rate,
+        sub_frame_rate,
+        tick_rate: number("tickRate").unwrap_or(if root.attribute("frameRate").is_some() {
+            frame_rate * sub_frame_rate
+        } else {
+            1.0
+        }),
+    };
+
+    let mut styles = HashMap::new();
+    for head in root.elements().filter(|element| element.name == "head") {
+        for styling in head.elements().filter(|element| element.name == "styling") {
+            for style in styling.elements().filter(|element| element.name == "style") {
+                if let Some(id) = style.attribute("id") {
+                    styles.insert(id.to_string(), style);
+                }
+            }
+        }
+    }
+
+    let mut cues = Vec::new();
+    if let Some(body) = root.elements().find(|element| element.name == "body") {
+        collect_cues(
+            input,
+            body,
+            MediaTime::from_millis(0),
+            None,
+            &timing,
+            &styles,
+            &mut cues,
+        )?;
+    }
+    cues.sort_by_key(|cue: &WebVTTCue| cue.start());
+
+    let mut file = WebVTTFile::new();
+    for cue in cues {
+        file.add(cue);
+    }
+    Ok(file)
+}
+
+fn error(input: &str, offset: usize, kind: SubtitleImportErrorKind) -> SubtitleImportError {
+    let before = &input[..offset.min(input.len())];
+    let line_start = before.rfind('\n').map_or(0, |position| position + 1);
+    SubtitleImportError {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+        kind,
+    }
+}
+
+/// Resolves the timing of an element, begin and end are relative to the parent's begin
+fn interval(
+    input: &str,
+    element: &Element,
+    parent_begin: MediaTime,
+    parent_end: Option<MediaTime>,
+    timing: &Timing,
+) -> Result<(MediaTime, Option<MediaTime>), SubtitleImportError> {
+    let time = |name: &str| -> Result<Option<MediaTime>, SubtitleImportError> {
+        match element.attribute(name) {
+            Some(value) => parse_time(value, timing).map(Some).ok_or_else(|| {
+                error(
+                    input,
+                    element.offset,
+                    SubtitleImportErrorKind::InvalidTimestamp,
+                )
+            }),
+            None => Ok(None),
+        }
+    };
+
+    let begin = parent_begin + time("begin")?.unwrap_or_else(|| MediaTime::from_millis(0));
+    let end = match (time("end")?, time("dur")?) {
+        (Some(end), _) => Some(parent_begin + end),
+        (None, Some(duration)) => Some(begin + duration),
+        (None, None) => parent_end,
+    };
+    // Children can't outlast their parent
+    let end = match (end, parent_end) {
+        (Some(end), Some(parent_end)) => Some(std::cmp::min(end, parent_end)),
+        (end, _) => end,
+    };
+    Ok((begin, end))
+}
+
+fn collect_cues(
+    input: &str,
+    element: &Element,
+    parent_begin: MediaTime,
+    parent_end: Option<MediaTime>,
+    timing: &Timing,
+    styles: &HashMap<String, &Element>,
+    cues: &mut Vec<WebVTTCue>,
+) -> Result<(), SubtitleImportError> {
+    let (begin, end) = interval(input, element, parent_begin, parent_end, timing)?;
+
+    if element.name == "p" {
+        // Paragraphs without end are shown indefinitely, which a cue can't express
+        let end = match end {
+            Some(end) if end > begin => end,
+            _ => return Ok(()),
+        };
+        let mut text = String::new();
+        append_text(element, styles, &mut text);
+        let payload = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !payload.is_empty() {
+            cues.push(WebVTTCue::new(begin, end, payload));
+        }
+        return Ok(());
+    }
+
+    for child in element.elements() {
+        collect_cues(input, child, begin, end, timing, styles, cues)?;
+    }
+    Ok(())
+}
+
+/// Appends the content of a paragraph or span, wrapped in the tags of its style
+fn append_text(element: &Element, styles: &HashMap<String, &Element>, text: &mut String) {
+    let style = text_style(element, styles, 0);
+    let tags = [
+        (style.bold, "b"),
+        (style.italic, "i"),
+        (style.underline, "u"),
+    ];
+    for (_, tag) in tags.iter().filter(|(enabled, _)| *enabled) {
+        text.push_str(&format!("<{}>", tag));
+    }
+
+    for child in &element.children {
+        match child {
+            Node::Text(content) => {
+                // Whitespace collapses, just like in HTML
+                let mut last_space = text.ends_with(' ') || text.ends_with('\n');
+                for c in content.chars() {
+                    if c.is_whitespace() {
+                        if !last_space {
+                            text.push(' ');
+                        }
+                        last_space = true;
+                    } else {
+                        text.push_str(&escape(&c.to_string()));
+                        last_space = false;
+                    }
+                }
+            }
+            Node::Element(child) if child.name == "br" => text.push('\n'),
+            Node::Element(child) if child.name == "span" => append_text(child, styles, text),
+            Node::Element(_) => {}
+        }
+    }
+
+    for (_, tag) in tags.iter().rev().filter(|(enabled, _)| *enabled) {
+        text.push_str(&format!("</{}>", tag));
+    }
+}
+
+/// Combines the referenced styles with the element's own style attributes
+fn text_style(element: &Element, styles: &HashMap<String, &Element>, depth: usize) -> TextStyle {
+    let mut style = TextStyle::default();
+    // Styles may reference each other, the depth guards against cycles
+    if depth < 8 {
+        for id in element.attribute("style").unwrap_or("").split_whitespace() {
+            if let Some(referenced) = styles.get(id) {
+                let referenced = text_style(referenced, styles, depth + 1);
+                style.bold |= referenced.bold;
+                style.italic |= referenced.italic;
+                style.underline |= referenced.underline;
+            }
+        }
+    }
+    if let Some(weight) = element.attribute("fontWeight") {
+        style.bold = weight.trim() == "bold";
+    }
+    if let Some(font_style) = element.attribute("fontStyle") {
+        style.italic = matches!(font_style.trim(), "italic" | "oblique");
+    }
+    if let Some(decoration) = element.attribute("textDecoration") {
+        style.underline = decoration
+            .split_whitespace()
+            .any(|value| value == "underline");
+    }
+    style
+}
+
+/// Parses clock times like `01:02:03.5` or `01:02:03:12` and offsets like `4.5s` or `30f`
+fn parse_time(value: &str, timing: &Timing) -> Option<MediaTime> {
+    let value = value.trim();
+    let seconds = if value.contains(':') {
+        let parts = value.split(':').collect::<Vec<_>>();
+        if parts.len() != 3 && parts.len() != 4 {
+            return None;
+        }
+        let hours = parse_number(parts[0])?;
+        let minutes = parse_number(parts[1])?;
+        let seconds = parse_number(parts[2])?;
+        let frames = match parts.get(3) {
+            Some(frames) => {
+                let (frames, sub_frames) = match frames.find('.') {
+                    Some(position) => (&frames[..position], parse_number(&frames[position + 1..])?),
+                    None => (*frames, 0.0),
+                };
+                parse_number(frames)? + sub_frames / timing.sub_frame_rate
+            }
+            None => 0.0,
+        };
+        hours * 3600.0 + minutes * 60.0 + seconds + frames / timing.frame_rate
+    } else {
+        let unit = value.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let number = parse_number(&value[..value.len() - unit.len()])?;
+        match unit {
+            "h" => number * 3600.0,
+            "m" => number * 60.0,
+            "s" => number,
+            "ms" => number / 1000.0,
+            "f" => number / timing.frame_rate,
+            "t" => number / timing.tick_rate,
+            _ => return None,
+        }
+    };
+
+    let nanos = (seconds * 1e9).round();
+    if nanos >= i64::MAX as f64 {
+        return None;
+    }
+    Some(MediaTime::from_nanos(nanos as i64))
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    if text.is_empty()
+        || !text
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || byte == b'.')
+    {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Elements nested deeper are rejected, as the reader recurses for every level
+const MAX_DEPTH: usize = 64;
+
+/// Reads the subset of XML used by subtitle files, errors carry the byte offset
+struct XmlReader<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips past the next occurrence of `end`
+    fn skip_past(&mut self, end: &str) -> Result<(), usize> {
+        match self.rest().find(end) {
+            Some(position) => {
+                self.position += position + end.len();
+                Ok(())
+            }
+            None => Err(self.position),
+        }
+    }
+
+    /// Skips the declaration, comments, processing instructions and the doctype
+    fn skip_misc(&mut self) -> Result<(), usize> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn document(mut self) -> Result<Element, usize> {
+        self.skip_misc()?;
+        let root = self.element(0)?;
+        self.skip_misc()?;
+        if !self.rest().is_empty() {
+            return Err(self.position);
+        }
+        Ok(root)
+    }
+
+    fn name(&mut self) -> Result<&'a str, usize> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.position);
+        }
+        self.position += length;
+        Ok(&rest[..length])
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element, usize> {
+        let offset = self.position;
+        if !self.rest().starts_with('<') || depth == MAX_DEPTH {
+            return Err(self.position);
+        }
+        self.position += 1;
+        let name = self.name()?;
+
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(Element {
+                    name: local_name(name),
+                    attributes,
+                    children: Vec::new(),
+                    offset,
+                });
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.position);
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => return Err(self.position),
+            };
+            self.position += 1;
+            let length = self.rest().find(quote).ok_or(self.position)?;
+            let value = decode_entities(&self.rest()[..length]).ok_or(self.position)?;
+            self.position += length + 1;
+            attributes.push((local_name(attribute), value));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += 2;
+                let end_tag = self.position;
+                if self.name()? != name {
+                    return Err(end_tag);
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.position);
+                }
+                self.position += 1;
+                break;
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let length = self.rest().find("]]>").ok_or(self.position)?;
+                children.push(Node::Text(self.rest()[..length].to_string()));
+                self.position += length + "]]>".len();
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                children.push(Node::Element(self.element(depth + 1)?));
+            } else if rest.is_empty() {
+                return Err(self.position);
+            } else {
+                let length = rest.find('<').unwrap_or(rest.len());
+                let text = decode_entities(&rest[..length]).ok_or(self.position)?;
+                children.push(Node::Text(text));
+                self.position += length;
+            }
+        }
+
+        Ok(Element {
+            name: local_name(name),
+            attributes,
+            children,
+            offset,
+        })
+    }
+}
+
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+fn decode_entities(text: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(position) = rest.find('&') {
+        result.push_str(&rest[..position]);
+        rest = &rest[position + 1..];
+        let end = rest.find(';')?;
+        let entity = &rest[..end];
+        let decoded = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                std::char::from_u32(code)?
+            }
+        };
+        result.push(decoded);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
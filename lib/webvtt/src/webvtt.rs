@@ -1,3 +1,4 @@
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::LineWriter;
@@ -8,38 +9,120 @@ use thiserror::Error;
 
 use media_time::MediaTime;
 
+use crate::parser::{self, WebVTTParseError};
+use crate::settings::{CueSettings, Region};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct WebVTTFile {
-    cues: Vec<WebVTTCue>,
+    description: Option<String>,
+    header: Vec<String>,
+    blocks: Vec<WebVTTBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebVTTBlock {
+    Cue(WebVTTCue),
+    /// Comment text, without the NOTE keyword
+    Note(String),
+    /// Stylesheet, without the STYLE keyword
+    Style(String),
+    Region(Region),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct WebVTTCue {
+    identifier: Option<String>,
     start: MediaTime,
     end: MediaTime,
+    settings: CueSettings,
     payload: String,
+    /// Hour digits of the parsed timestamps, `None` writes hours only when needed
+    start_hours: Option<usize>,
+    end_hours: Option<usize>,
+    /// Settings as parsed, including unknown ones, written until the settings are replaced
+    settings_text: Option<String>,
 }
 
 #[derive(Error, Debug)]
 pub enum WebVTTError {
     #[error("Error saving file {0}")]
     IoError(PathBuf, #[source] std::io::Error),
+    #[error("Error reading file")]
+    ReadError(#[source] std::io::Error),
+    #[error("Error writing file")]
+    WriteError(#[source] std::io::Error),
+    #[error(transparent)]
+    ParseError(#[from] WebVTTParseError),
 }
 
 impl WebVTTFile {
     pub fn new() -> WebVTTFile {
-        WebVTTFile { cues: Vec::new() }
+        WebVTTFile {
+            description: None,
+            header: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Parses a file, serializing the result again yields the same text for files with `\n`
+    /// line endings, single blank lines between blocks and single spaces around `-->`
+    pub fn parse(input: &str) -> Result<WebVTTFile, WebVTTParseError> {
+        parser::parse(input)
+    }
+
+    pub fn from_reader(mut reader: impl Read) -> Result<WebVTTFile, WebVTTError> {
+        let mut input = String::new();
+        reader
+            .read_to_string(&mut input)
+            .map_err(WebVTTError::ReadError)?;
+        Ok(parser::parse(&input)?)
+    }
+
+    /// Text following the WEBVTT signature on the first line
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    /// Lines between the signature and the first block, e.g. `X-TIMESTAMP-MAP`
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    pub fn add_header(&mut self, line: String) {
+        self.header.push(line);
+    }
+
+    pub fn blocks(&self) -> &[WebVTTBlock] {
+        &self.blocks
+    }
+
+    pub fn cues(&self) -> impl Iterator<Item = &WebVTTCue> {
+        self.blocks.iter().filter_map(|block| match block {
+            WebVTTBlock::Cue(cue) => Some(cue),
+            _ => None,
+        })
     }
 
     pub fn add(&mut self, cue: WebVTTCue) {
-        self.cues.push(cue);
+        self.blocks.push(WebVTTBlock::Cue(cue));
+    }
+
+    pub fn add_block(&mut self, block: WebVTTBlock) {
+        self.blocks.push(block);
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), WebVTTError> {
+        write!(writer, "{}", self).map_err(WebVTTError::WriteError)
     }
 
     fn save_impl(&self, path: &impl AsRef<Path>) -> Result<(), std::io::Error> {
         let file = File::create(path)?;
         let mut file = LineWriter::new(file);
-        file.write_all(b"WEBVTT\n\n")?;
-        for cue in &self.cues {
-            cue.save(&mut file)?;
-        }
+        write!(file, "{}", self)?;
         file.flush()?;
         Ok(())
     }
@@ -50,20 +133,174 @@ impl WebVTTFile {
     }
 }
 
+impl fmt::Display for WebVTTFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&header(self.description.as_deref(), &self.header))?;
+        for block in &self.blocks {
+            write!(f, "{}\n\n", block)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for WebVTTFile {
+    fn default() -> Self {
+        WebVTTFile::new()
+    }
+}
+
+/// Writes the block without the blank line separating it from the next one
+impl fmt::Display for WebVTTBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebVTTBlock::Cue(cue) => write!(f, "{}", cue),
+            WebVTTBlock::Note(text) => {
+                let text = sanitize_block(text);
+                // Single line comments stay on the keyword line
+                if text.is_empty() {
+                    write!(f, "NOTE")
+                } else if !text.contains('\n') {
+                    write!(f, "NOTE {}", text)
+                } else {
+                    write!(f, "NOTE\n{}", text)
+                }
+            }
+            WebVTTBlock::Style(text) => write!(f, "STYLE\n{}", sanitize_block(text)),
+            WebVTTBlock::Region(region) => write!(f, "REGION\n{}", region),
+        }
+    }
+}
+
 impl WebVTTCue {
     pub fn new(start: MediaTime, end: MediaTime, payload: String) -> WebVTTCue {
         WebVTTCue {
+            identifier: None,
             start,
             end,
+            settings: CueSettings::default(),
             payload,
+            start_hours: None,
+            end_hours: None,
+            settings_text: None,
         }
     }
 
-    fn save(&self, writer: &mut LineWriter<File>) -> Result<(), std::io::Error> {
-        writer.write_all(format!("{} --> {}\n", self.start, self.end).as_bytes())?;
-        writer.write_all(self.payload.as_bytes())?;
-        writer.write_all(b"\n\n")?;
+    pub fn with_identifier(mut self, identifier: Option<String>) -> Self {
+        self.identifier = identifier;
+        self
+    }
 
-        Ok(())
+    pub fn with_settings(mut self, settings: CueSettings) -> Self {
+        self.settings = settings;
+        self.settings_text = None;
+        self
+    }
+
+    /// Keeps the form of the parsed timings, so unchanged cues are written back verbatim
+    pub(crate) fn with_source(
+        mut self,
+        start_hours: Option<usize>,
+        end_hours: Option<usize>,
+        settings_text: &str,
+    ) -> Self {
+        self.start_hours = start_hours;
+        self.end_hours = end_hours;
+        self.settings_text = Some(settings_text.to_string());
+        self
+    }
+
+    pub fn identifier(&self) -> Option<&str> {
+        self.identifier.as_deref()
+    }
+
+    pub fn start(&self) -> MediaTime {
+        self.start
+    }
+
+    pub fn end(&self) -> MediaTime {
+        self.end
+    }
+
+    pub fn settings(&self) -> &CueSettings {
+        &self.settings
+    }
+
+    /// Payload markup, text has to be escaped with [`escape`]
+    pub fn payload(&self) -> &str {
+        &self.payload
     }
 }
+
+impl fmt::Display for WebVTTCue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(identifier) = &self.identifier {
+            writeln!(f, "{}", sanitize_line(identifier))?;
+        }
+        let start = timestamp(self.start, self.start_hours);
+        let end = timestamp(self.end, self.end_hours);
+        let settings = match &self.settings_text {
+            Some(text) => text.clone(),
+            None => self.settings.to_string(),
+        };
+        if settings.is_empty() {
+            writeln!(f, "{} --> {}", start, end)?;
+        } else {
+            writeln!(f, "{} --> {} {}", start, end, settings)?;
+        }
+        write!(f, "{}", sanitize_block(&self.payload))
+    }
+}
+
+/// Writes `[hh:]mm:ss.ttt` with at least the given number of hour digits, or with hours only
+/// if they aren't zero. WebVTT has no negative timestamps, so earlier times are written as zero.
+fn timestamp(time: MediaTime, hour_digits: Option<usize>) -> String {
+    let time = std::cmp::max(time, MediaTime::from_millis(0));
+    let digits = match hour_digits {
+        Some(digits) => digits,
+        None => return time.to_string(),
+    };
+    let millis = time.milliseconds();
+    format!(
+        "{:0width$}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000,
+        width = digits
+    )
+}
+
+/// The signature line and the header lines, followed by the blank line ending the header
+pub(crate) fn header(description: Option<&str>, header: &[String]) -> String {
+    let mut text = match description {
+        Some(description) => format!("WEBVTT {}\n", sanitize_line(description)),
+        None => String::from("WEBVTT\n"),
+    };
+    for line in header {
+        text.push_str(&sanitize_line(line));
+        text.push('\n');
+    }
+    text.push('\n');
+    text
+}
+
+/// Escapes plain text for use in a cue payload
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Blank lines would end the block early, and a line containing `-->` would start a new cue
+fn sanitize_block(text: &str) -> String {
+    parser::split_lines(text)
+        .iter()
+        .filter(|line| !line.text.is_empty())
+        .map(|line| line.text.replace("-->", "--&gt;"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn sanitize_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ").replace("-->", "--&gt;")
+}
@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::webvtt::{header, WebVTTBlock, WebVTTCue, WebVTTError};
+
+/// Writes a file block by block, flushing after every block so readers see cues as they
+/// are produced
+pub struct WebVTTWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> WebVTTWriter<W> {
+    pub fn new(writer: W) -> Result<WebVTTWriter<W>, WebVTTError> {
+        WebVTTWriter::with_header(writer, None, &[])
+    }
+
+    pub fn with_header(
+        mut writer: W,
+        description: Option<&str>,
+        header_lines: &[String],
+    ) -> Result<WebVTTWriter<W>, WebVTTError> {
+        writer
+            .write_all(header(description, header_lines).as_bytes())
+            .map_err(WebVTTError::WriteError)?;
+        Ok(WebVTTWriter { writer })
+    }
+
+    pub fn write_block(&mut self, block: &WebVTTBlock) -> Result<(), WebVTTError> {
+        write!(self.writer, "{}\n\n", block).map_err(WebVTTError::WriteError)?;
+        self.flush()
+    }
+
+    pub fn write_cue(&mut self, cue: &WebVTTCue) -> Result<(), WebVTTError> {
+        write!(self.writer, "{}\n\n", cue).map_err(WebVTTError::WriteError)?;
+        self.flush()
+    }
+
+    pub fn flush(&mut self) -> Result<(), WebVTTError> {
+        self.writer.flush().map_err(WebVTTError::WriteError)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl WebVTTWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, WebVTTError> {
+        let file = File::create(path.as_ref())
+            .map_err(|err| WebVTTError::IoError(path.as_ref().to_path_buf(), err))?;
+        WebVTTWriter::new(BufWriter::new(file))
+    }
+}
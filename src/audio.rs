@@ -0,0 +1,151 @@
+use anyhow::format_err;
+use ffmpeg_api::api::*;
+use fraction::Fraction;
+use media_time::MediaTime;
+
+use crate::loudness::{self, Loudness, LoudnessMeter};
+use crate::waveform::{Waveform, WaveformBuilder};
+
+/// Decodes a single audio stream, measuring its loudness and optionally building a waveform
+pub(crate) struct AudioAnalyzer {
+    index: i32,
+    codec_name: String,
+    time_base: Fraction,
+    codec_context: AVCodecContext,
+    frame: AVFrame,
+    waveform_samples_per_pixel: Option<u32>,
+    state: Option<AnalyzerState>,
+    frames_decoded: u64,
+}
+
+// Created from the first decoded frame, as the sample layout is only known then
+struct AnalyzerState {
+    resampler: SwrContext<f32>,
+    channels: usize,
+    loudness: LoudnessMeter,
+    waveform: Option<WaveformBuilder>,
+}
+
+pub(crate) struct AudioAnalysis {
+    pub index: i32,
+    pub loudness: Loudness,
+    pub waveform: Option<Waveform>,
+}
+
+impl AudioAnalyzer {
+    pub fn new(stream: &AVStream, waveform_samples_per_pixel: Option<u32>) -> anyhow::Result<Self> {
+        let codec_parameters = stream.codec_parameters()?;
+        let local_codec = codec_parameters.find_decoder()?;
+
+        let mut codec_context = AVCodecContext::new(&local_codec)
+            .map_err(|error| format_err!("Could not init codec context: {}", error))?;
+        codec_context.set_parameters(&codec_parameters);
+        codec_context
+            .open(&local_codec, None)
+            .map_err(|error| format_err!("Could not open codec: {}", error))?;
+
+        Ok(AudioAnalyzer {
+            index: stream.index(),
+            codec_name: local_codec.name()?,
+            time_base: stream.time_base(),
+            codec_context,
+            frame: AVFrame::new()
+                .map_err(|error| format_err!("Could not create input frame: {}", error))?,
+            waveform_samples_per_pixel,
+            state: None,
+            frames_decoded: 0,
+        })
+    }
+
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
+    pub fn codec_name(&self) -> &str {
+        &self.codec_name
+    }
+
+    pub fn frames_decoded(&self) -> u64 {
+        self.frames_decoded
+    }
+
+    /// Decodes a packet of this stream and returns the timestamp of the last decoded frame
+    pub fn decode(&mut self, packet: &mut AVPacket) -> anyhow::Result<Option<MediaTime>> {
+        self.codec_context
+            .in_packet(packet)
+            .map_err(|error| format_err!("Could not load packet: {}", error))?;
+
+        let mut timestamp = None;
+        while self.codec_context.out_frame(&mut self.frame).is_ok() {
+            self.frames_decoded += 1;
+            if let Ok(Some(frame_timestamp)) = crate::frame_timestamp(&self.frame, &self.time_base)
+            {
+                timestamp = Some(frame_timestamp);
+            }
+
+            if self.state.is_none() {
+                self.state = Some(AnalyzerState::new(
+                    &self.frame,
+                    self.waveform_samples_per_pixel,
+                )?);
+            }
+            if let Some(state) = self.state.as_mut() {
+                let samples = state
+                    .resampler
+                    .convert(&self.frame)
+                    .map_err(|error| format_err!("Could not resample audio: {}", error))?;
+                state.add_samples(&samples);
+            }
+        }
+
+        Ok(timestamp)
+    }
+
+    /// Flushes the resampler, returns nothing if no frame could be decoded
+    pub fn finish(self) -> anyhow::Result<Option<AudioAnalysis>> {
+        let mut state = match self.state {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        let samples = state
+            .resampler
+            .flush()
+            .map_err(|error| format_err!("Could not resample audio: {}", error))?;
+        state.add_samples(&samples);
+
+        Ok(Some(AudioAnalysis {
+            index: self.index,
+            loudness: state.loudness.finish(),
+            waveform: state.waveform.map(WaveformBuilder::finish),
+        }))
+    }
+}
+
+impl AnalyzerState {
+    fn new(frame: &AVFrame, waveform_samples_per_pixel: Option<u32>) -> anyhow::Result<Self> {
+        // Loudness weights depend on the channel positions, so the layout is kept as is
+        let layout = frame.layout();
+        let resampler = SwrContext::new(frame, layout, frame.sample_rate())
+            .map_err(|error| format_err!("Could not init resampler: {}", error))?;
+
+        Ok(AnalyzerState {
+            resampler,
+            channels: layout.channels().max(0) as usize,
+            loudness: LoudnessMeter::new(
+                frame.sample_rate().max(0) as u32,
+                loudness::channel_weights(layout),
+            ),
+            waveform: waveform_samples_per_pixel.map(|samples_per_pixel| {
+                WaveformBuilder::new(frame.sample_rate(), samples_per_pixel)
+            }),
+        })
+    }
+
+    fn add_samples(&mut self, samples: &[f32]) {
+        self.loudness.add_samples(samples);
+        if let Some(waveform) = self.waveform.as_mut() {
+            waveform.add_interleaved(samples, self.channels);
+        }
+    }
+}
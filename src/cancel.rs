@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::ExtractError;
+
+/// Shared flag to stop a running extraction from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Interrupt {
+    token: CancellationToken,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Interrupt {
+    pub(crate) fn new(token: CancellationToken, timeout: Option<Duration>) -> Self {
+        Interrupt {
+            token,
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    pub(crate) fn status(&self) -> Option<ExtractError> {
+        if self.token.is_cancelled() {
+            return Some(ExtractError::Cancelled);
+        }
+
+        match (self.timeout, self.deadline) {
+            (Some(timeout), Some(deadline)) if Instant::now() >= deadline => {
+                Some(ExtractError::TimedOut(timeout))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), ExtractError> {
+        match self.status() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
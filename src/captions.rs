@@ -0,0 +1,345 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Error};
+use media_time::MediaTime;
+use webvtt::{escape, WebVTTCue, WebVTTFile};
+
+const ROWS: usize = 15;
+const COLUMNS: usize = 32;
+
+type Memory = [[Option<char>; COLUMNS]; ROWS];
+
+// First row (1-based) addressed by a preamble address code, indexed by its first byte
+const PREAMBLE_ROWS: [usize; 8] = [11, 1, 3, 12, 14, 5, 7, 9];
+
+const SPECIAL_CHARACTERS: &str = "®°½¿™¢£♪à\u{a0}èâêîôû";
+const EXTENDED_CHARACTERS_SPANISH_FRENCH: &str = "ÁÉÓÚÜü‘¡*'—©℠•“”ÀÂÇÈÊËëÎÏïÔÙùÛ«»";
+const EXTENDED_CHARACTERS_PORTUGUESE_GERMAN: &str = "ÃãÍÌìÒòÕõ{}\\^_|~ÄäÖöß¥¤│ÅåØø┌┐└┘";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mode {
+    PopOn,
+    RollUp(usize),
+    PaintOn,
+    Text,
+}
+
+struct Caption {
+    start: MediaTime,
+    end: MediaTime,
+    text: String,
+}
+
+/// Decodes the CC1 channel of CEA-608 captions carried in ATSC A53 `cc_data`.
+/// CEA-708 service blocks are ignored, as virtually all broadcasts also carry 608 captions.
+pub struct CaptionDecoder {
+    mode: Mode,
+    displayed: Memory,
+    non_displayed: Memory,
+    row: usize,
+    column: usize,
+    // Characters belong to the data channel of the last control code
+    channel: u8,
+    last_control: Option<(u8, u8)>,
+    current: Option<(MediaTime, String)>,
+    captions: Vec<Caption>,
+}
+
+impl CaptionDecoder {
+    pub fn new() -> Self {
+        CaptionDecoder {
+            mode: Mode::PopOn,
+            displayed: [[None; COLUMNS]; ROWS],
+            non_displayed: [[None; COLUMNS]; ROWS],
+            row: ROWS - 1,
+            column: 0,
+            channel: 0,
+            last_control: None,
+            current: None,
+            captions: Vec::new(),
+        }
+    }
+
+    /// Decodes the `cc_data` of one frame, a sequence of 3 byte constructs
+    pub fn decode(&mut self, cc_data: &[u8], timestamp: MediaTime) {
+        for construct in cc_data.chunks_exact(3) {
+            let cc_valid = construct[0] & 0x04 != 0;
+            let cc_type = construct[0] & 0x03;
+            // Type 0 is field 1 of NTSC line 21, which carries CC1 and CC2
+            if cc_valid && cc_type == 0 {
+                self.decode_pair(construct[1], construct[2], timestamp);
+            }
+        }
+    }
+
+    fn decode_pair(&mut self, first: u8, second: u8, timestamp: MediaTime) {
+        if !odd_parity(first) || !odd_parity(second) {
+            return;
+        }
+        let (first, second) = (first & 0x7f, second & 0x7f);
+        if first == 0 && second == 0 {
+            return;
+        }
+
+        if (0x10..=0x1f).contains(&first) {
+            // Control codes are transmitted twice for redundancy
+            if self.last_control == Some((first, second)) {
+                self.last_control = None;
+                return;
+            }
+            self.last_control = Some((first, second));
+            self.channel = (first >> 3) & 1;
+            if self.channel == 0 {
+                self.control(first, second, timestamp);
+            }
+        } else {
+            self.last_control = None;
+            if self.channel != 0 || first < 0x20 {
+                return;
+            }
+            self.put_char(basic_character(first));
+            if second >= 0x20 {
+                self.put_char(basic_character(second));
+            }
+        }
+    }
+
+    fn control(&mut self, first: u8, second: u8, timestamp: MediaTime) {
+        match (first, second) {
+            (0x14, 0x20..=0x2f) | (0x15, 0x20..=0x2f) => self.command(second, timestamp),
+            (0x17, 0x21..=0x23) => {
+                self.column = (self.column + (second - 0x20) as usize).min(COLUMNS - 1);
+            }
+            // Mid-row style changes are displayed as a space
+            (0x11, 0x20..=0x2f) => self.put_char(' '),
+            (0x11, 0x30..=0x3f) => {
+                self.put_char(table_character(SPECIAL_CHARACTERS, second - 0x30));
+            }
+            // Extended characters replace the fallback character sent before them
+            (0x12, 0x20..=0x3f) => {
+                self.backspace();
+                self.put_char(table_character(
+                    EXTENDED_CHARACTERS_SPANISH_FRENCH,
+                    second - 0x20,
+                ));
+            }
+            (0x13, 0x20..=0x3f) => {
+                self.backspace();
+                self.put_char(table_character(
+                    EXTENDED_CHARACTERS_PORTUGUESE_GERMAN,
+                    second - 0x20,
+                ));
+            }
+            (_, 0x40..=0x7f) => self.preamble(first, second),
+            _ => {}
+        }
+    }
+
+    fn command(&mut self, command: u8, timestamp: MediaTime) {
+        match command {
+            // Resume caption loading
+            0x20 => self.mode = Mode::PopOn,
+            // Backspace
+            0x21 => self.backspace(),
+            // Delete to end of row
+            0x24 => {
+                let (row, column) = (self.row, self.column);
+                if let Some(memory) = self.target() {
+                    for cell in &mut memory[row][column..] {
+                        *cell = None;
+                    }
+                }
+            }
+            // Roll-up captions with 2, 3 or 4 rows
+            0x25..=0x27 => {
+                // Switching from another mode erases the screen
+                if !matches!(self.mode, Mode::RollUp(_)) {
+                    self.displayed = [[None; COLUMNS]; ROWS];
+                    self.non_displayed = [[None; COLUMNS]; ROWS];
+                    self.flush(timestamp);
+                    self.row = ROWS - 1;
+                    self.column = 0;
+                }
+                self.mode = Mode::RollUp((command - 0x23) as usize);
+            }
+            // Resume direct captioning
+            0x29 => self.mode = Mode::PaintOn,
+            // Text restart and resume text display
+            0x2a | 0x2b => self.mode = Mode::Text,
+            // Erase displayed memory
+            0x2c => {
+                self.displayed = [[None; COLUMNS]; ROWS];
+                self.flush(timestamp);
+            }
+            // Carriage return
+            0x2d => {
+                if let Mode::RollUp(rows) = self.mode {
+                    self.flush(timestamp);
+                    let top = (self.row + 1).saturating_sub(rows);
+                    for row in top..self.row {
+                        self.displayed[row] = self.displayed[row + 1];
+                    }
+                    self.displayed[self.row] = [None; COLUMNS];
+                    self.column = 0;
+                }
+            }
+            // Erase non-displayed memory
+            0x2e => self.non_displayed = [[None; COLUMNS]; ROWS],
+            // End of caption, flips the memories
+            0x2f => {
+                std::mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.mode = Mode::PopOn;
+                self.flush(timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    fn preamble(&mut self, first: u8, second: u8) {
+        let base = PREAMBLE_ROWS[(first & 0x07) as usize];
+        let row = if first & 0x07 != 0 && second & 0x20 != 0 {
+            base
+        } else {
+            base - 1
+        };
+
+        // Roll-up captions move along with their base row
+        if let Mode::RollUp(rows) = self.mode {
+            if row != self.row {
+                let mut moved = [[None; COLUMNS]; ROWS];
+                for offset in 0..rows {
+                    if let (Some(from), Some(to)) =
+                        (self.row.checked_sub(offset), row.checked_sub(offset))
+                    {
+                        moved[to] = self.displayed[from];
+                    }
+                }
+                self.displayed = moved;
+            }
+        }
+
+        self.row = row;
+        self.column = if second & 0x10 != 0 {
+            ((second & 0x0e) >> 1) as usize * 4
+        } else {
+            0
+        };
+    }
+
+    fn target(&mut self) -> Option<&mut Memory> {
+        match self.mode {
+            Mode::PopOn => Some(&mut self.non_displayed),
+            Mode::RollUp(_) | Mode::PaintOn => Some(&mut self.displayed),
+            Mode::Text => None,
+        }
+    }
+
+    fn put_char(&mut self, character: char) {
+        let (row, column) = (self.row, self.column);
+        if let Some(memory) = self.target() {
+            memory[row][column] = Some(character);
+            self.column = (column + 1).min(COLUMNS - 1);
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.column == 0 {
+            return;
+        }
+        self.column -= 1;
+        let (row, column) = (self.row, self.column);
+        if let Some(memory) = self.target() {
+            memory[row][column] = None;
+        }
+    }
+
+    /// Ends the current caption if the displayed text changed
+    fn flush(&mut self, timestamp: MediaTime) {
+        let text = memory_text(&self.displayed);
+        if self.current.as_ref().map(|(_, current)| current) == Some(&text) {
+            return;
+        }
+
+        if let Some((start, current)) = self.current.take() {
+            if timestamp > start {
+                self.captions.push(Caption {
+                    start,
+                    end: timestamp,
+                    text: current,
+                });
+            }
+        }
+        if !text.is_empty() {
+            self.current = Some((timestamp, text));
+        }
+    }
+
+    /// Writes the captions, one still on screen is shown until `end`
+    pub fn save(
+        mut self,
+        output_path: &Path,
+        name: &str,
+        end: MediaTime,
+    ) -> Result<Option<(PathBuf, WebVTTFile)>, Error> {
+        self.displayed = [[None; COLUMNS]; ROWS];
+        self.flush(end);
+        if self.captions.is_empty() {
+            return Ok(None);
+        }
+
+        let mut track = WebVTTFile::new();
+        for caption in self.captions {
+            track.add(WebVTTCue::new(caption.start, caption.end, caption.text));
+        }
+
+        let path = output_path.join(format!("{}.vtt", name));
+        track
+            .save(&path)
+            .map_err(|error| format_err!("Could not write caption track: {}", error))?;
+
+        Ok(Some((path, track)))
+    }
+}
+
+impl Default for CaptionDecoder {
+    fn default() -> Self {
+        CaptionDecoder::new()
+    }
+}
+
+fn odd_parity(byte: u8) -> bool {
+    byte.count_ones() % 2 == 1
+}
+
+/// The basic character set is ASCII with a few replacements
+fn basic_character(byte: u8) -> char {
+    match byte {
+        0x2a => 'á',
+        0x5c => 'é',
+        0x5e => 'í',
+        0x5f => 'ó',
+        0x60 => 'ú',
+        0x7b => 'ç',
+        0x7c => '÷',
+        0x7d => 'Ñ',
+        0x7e => 'ñ',
+        0x7f => '█',
+        byte => byte as char,
+    }
+}
+
+fn table_character(table: &str, index: u8) -> char {
+    table.chars().nth(index as usize).unwrap_or(' ')
+}
+
+fn memory_text(memory: &Memory) -> String {
+    memory
+        .iter()
+        .map(|row| {
+            let line: String = row.iter().map(|cell| cell.unwrap_or(' ')).collect();
+            escape(line.trim())
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
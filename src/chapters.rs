@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Error};
+use ffmpeg_api::api::AVFormatContext;
+use media_time::MediaTime;
+use serde::{Deserialize, Serialize};
+use webvtt::{escape, WebVTTCue, WebVTTFile};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub id: i32,
+    #[serde(with = "crate::seconds")]
+    pub start: MediaTime,
+    #[serde(with = "crate::seconds")]
+    pub end: MediaTime,
+    pub title: Option<String>,
+}
+
+pub fn read_chapters(avformat_context: &AVFormatContext) -> Result<Vec<Chapter>, Error> {
+    avformat_context
+        .chapters()
+        .map(|chapter| {
+            Ok(Chapter {
+                id: chapter.id(),
+                start: chapter.start_time()?,
+                end: chapter.end_time()?,
+                title: chapter.title(),
+            })
+        })
+        .collect()
+}
+
+pub fn save_chapters(
+    chapters: &[Chapter],
+    output_path: &Path,
+    name: &str,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut metadata = WebVTTFile::new();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let title = match &chapter.title {
+            Some(title) => escape(title),
+            None => format!("Chapter {}", index + 1),
+        };
+        // Identifiers allow players to link to a chapter
+        metadata.add(
+            WebVTTCue::new(chapter.start, chapter.end, title)
+                .with_identifier(Some(chapter.id.to_string())),
+        );
+    }
+    let track_path = output_path.join(format!("{}.vtt", name));
+    metadata
+        .save(&track_path)
+        .map_err(|error| format_err!("Could not write chapter track: {}", error))?;
+
+    let list_path = output_path.join(format!("{}.json", name));
+    let file = File::create(&list_path)
+        .map_err(|error| format_err!("Could not create chapter list: {}", error))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), chapters)
+        .map_err(|error| format_err!("Could not write chapter list: {}", error))?;
+
+    Ok(vec![track_path, list_path])
+}
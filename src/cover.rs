@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Error};
+use ffmpeg_api::api::{AVFormatContext, AVStream};
+use ffmpeg_api::enums::{AVCodecID, AVDisposition};
+
+pub fn is_attached_pic(stream: &AVStream) -> bool {
+    stream.disposition().contains(AVDisposition::ATTACHED_PIC)
+}
+
+fn extension(codec_id: AVCodecID) -> Option<&'static str> {
+    match codec_id {
+        AVCodecID::MJPEG => Some("jpeg"),
+        AVCodecID::PNG => Some("png"),
+        AVCodecID::BMP => Some("bmp"),
+        AVCodecID::GIF => Some("gif"),
+        AVCodecID::WEBP => Some("webp"),
+        AVCodecID::TIFF => Some("tiff"),
+        _ => None,
+    }
+}
+
+/// Writes the first embedded cover art as-is, without re-encoding it
+pub fn save_cover(
+    avformat_context: &AVFormatContext,
+    output_path: &Path,
+    name: &str,
+) -> Result<Option<PathBuf>, Error> {
+    let stream = match avformat_context.streams().find(is_attached_pic) {
+        Some(stream) => stream,
+        None => return Ok(None),
+    };
+    let data = match stream.attached_pic() {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+
+    let codec_id = stream.codec_parameters()?.codec_id();
+    let extension = codec_id
+        .and_then(extension)
+        .ok_or_else(|| format_err!("Unsupported cover art format: {:?}", codec_id))?;
+
+    let path = output_path.join(format!("{}.{}", name, extension));
+    File::create(&path)
+        .and_then(|mut file| file.write_all(data))
+        .map_err(|error| format_err!("Could not write cover art: {}", error))?;
+
+    Ok(Some(path))
+}
@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq)]
+pub enum ExtractError {
+    #[error("Extraction was cancelled")]
+    Cancelled,
+    #[error("Extraction timed out after {0:?}")]
+    TimedOut(Duration),
+}
@@ -1,52 +1,382 @@
 #![allow(dead_code)]
 
+#[cfg(test)]
+mod tests;
+
+pub mod cancel;
+pub mod captions;
+pub mod chapters;
+pub mod cover;
+pub mod error;
+pub mod loudness;
+pub mod metadata;
+pub mod observer;
+pub mod options;
+pub mod report;
 pub mod spritesheet;
+pub mod subtitles;
+pub mod thumbnail;
+pub mod waveform;
+
+mod audio;
+mod seconds;
 
-use std::path::Path;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
 
 use anyhow::format_err;
 use ffmpeg_api::api::*;
 use ffmpeg_api::enums::*;
-use image::ImageOutputFormat;
+use fraction::Fraction;
+use media_time::MediaTime;
+use serde::{Deserialize, Serialize};
+use webvtt::hls::HlsSegmenter;
+use webvtt::WebVTTFile;
 
-pub fn extract(
-    max_size: u32,
-    num_horizontal: u32,
-    num_vertical: u32,
-    frame_interval: media_time::MediaTime,
-    input_file: &Path,
-    output_folder: &Path,
-    format: ImageOutputFormat,
+pub use crate::cancel::CancellationToken;
+pub use crate::error::ExtractError;
+pub use crate::observer::ExtractObserver;
+pub use crate::options::ExtractOptions;
+pub use crate::report::ExtractReport;
+
+pub enum MediaSource {
+    Path(PathBuf),
+    Reader(Box<dyn ReadSeek>),
+}
+
+impl MediaSource {
+    pub fn reader(reader: impl Read + Seek + 'static) -> Self {
+        MediaSource::Reader(Box::new(reader))
+    }
+}
+
+impl From<&Path> for MediaSource {
+    fn from(path: &Path) -> Self {
+        MediaSource::Path(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for MediaSource {
+    fn from(path: PathBuf) -> Self {
+        MediaSource::Path(path)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplingMode {
+    /// Reads every packet of the file and keeps the frames that fulfil the frame interval
+    Sequential,
+    /// Seeks to every sample position and only decodes the next keyframe
+    Seek,
+}
+
+struct FrameSampler {
+    spritesheet_manager: spritesheet::SpritesheetManager,
+    thumbnail_selector: thumbnail::ThumbnailSelector,
+    output_frame: AVFrame,
+    scale_context: SwsContext,
     scaler: SwsScaler,
     flags: SwsFlags,
+    frames_sampled: u64,
+    last_timestamp: Option<MediaTime>,
+}
+
+impl FrameSampler {
+    fn sample(
+        &mut self,
+        frame: &AVFrame,
+        timestamp: MediaTime,
+        observer: &mut dyn ExtractObserver,
+        report: &mut ExtractReport,
+    ) -> anyhow::Result<()> {
+        // Sprites have to be in order, so frames going back in time, e.g. after a timestamp
+        // discontinuity, are skipped
+        if self.last_timestamp.map_or(false, |last| timestamp <= last) {
+            return Ok(());
+        }
+        self.last_timestamp = Some(timestamp);
+
+        if !self.spritesheet_manager.initialized() {
+            self.spritesheet_manager
+                .initialize(frame.width() as u32, frame.height() as u32)?;
+            // The metadata is written while sampling, so it has to be removed on failure
+            report.files.push(self.spritesheet_manager.metadata_path());
+            self.output_frame
+                .init(
+                    self.spritesheet_manager.sprite_width() as i32,
+                    self.spritesheet_manager.sprite_height() as i32,
+                    AVPixelFormat::RGB24,
+                )
+                .map_err(|error| format_err!("Could not init output frame: {}", error))?;
+            self.scale_context
+                .reinit(frame, &self.output_frame, self.scaler, self.flags)
+                .map_err(|error| format_err!("Could not reinit scale context: {}", error))?;
+        }
+
+        self.scale_context.scale(frame, &mut self.output_frame);
+
+        let image = image::ImageBuffer::from_raw(
+            self.output_frame.width() as u32,
+            self.output_frame.height() as u32,
+            self.output_frame.data(0).to_vec(),
+        )
+        .ok_or_else(|| format_err!("Could not process frame"))?;
+
+        self.frames_sampled += 1;
+        self.thumbnail_selector.offer(frame, timestamp, &image)?;
+
+        let written = self.spritesheet_manager.files().len();
+        self.spritesheet_manager.add_image(timestamp, image)?;
+        self.collect_sheets(written, observer, report);
+
+        Ok(())
+    }
+
+    fn collect_sheets(
+        &self,
+        written: usize,
+        observer: &mut dyn ExtractObserver,
+        report: &mut ExtractReport,
+    ) {
+        for path in &self.spritesheet_manager.files()[written..] {
+            observer.sheet_written(path);
+            report.files.push(path.clone());
+        }
+    }
+}
+
+fn warn(report: &mut ExtractReport, observer: &mut dyn ExtractObserver, message: &str) {
+    observer.warning(message);
+    report.warnings.push(String::from(message));
+}
+
+pub fn extract(
+    input: impl Into<MediaSource>,
+    output_folder: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<ExtractReport> {
+    extract_with_observer(input, output_folder, options, &mut observer::NoopObserver)
+}
+
+pub fn extract_with_observer(
+    input: impl Into<MediaSource>,
+    output_folder: &Path,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+) -> anyhow::Result<ExtractReport> {
+    options.validate()?;
+    let interrupt = cancel::Interrupt::new(options.cancellation.clone(), options.timeout);
+    let mut report = ExtractReport::default();
+
+    if let Err(error) = extract_into(
+        input.into(),
+        output_folder,
+        options,
+        observer,
+        &interrupt,
+        &mut report,
+    ) {
+        return Err(match interrupt.status() {
+            Some(interrupted) => {
+                remove_outputs(output_folder, &report.files);
+                interrupted.into()
+            }
+            None => error,
+        });
+    }
+
+    observer.finished(&report);
+    Ok(report)
+}
+
+fn remove_outputs(output_folder: &Path, files: &[PathBuf]) {
+    for file in files {
+        let _ = std::fs::remove_file(file);
+    }
+    // Only succeeds if the directory is empty, so files not created by us are kept
+    let _ = std::fs::remove_dir(output_folder.join("spritesheets"));
+    let _ = std::fs::remove_dir(output_folder.join("subtitles"));
+}
+
+fn extract_into(
+    input: MediaSource,
+    output_folder: &Path,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+    interrupt: &cancel::Interrupt,
+    report: &mut ExtractReport,
 ) -> anyhow::Result<()> {
     let mut avformat_context = AVFormatContext::new()?;
-    avformat_context.open_input(input_file)?;
-    let duration = avformat_context.duration()?;
+    let callback_interrupt = interrupt.clone();
+    avformat_context.set_interrupt_callback(move || callback_interrupt.status().is_some());
+    match input {
+        MediaSource::Path(path) => avformat_context.open_input(&path, None)?,
+        MediaSource::Reader(reader) => avformat_context.open_reader(reader, None)?,
+    }
+    if let Err(error) = avformat_context.find_stream_info() {
+        warn(report, observer, &format!("{}", error));
+    }
+
+    let chapters = chapters::read_chapters(&avformat_context)?;
+    if !chapters.is_empty() {
+        report.files.extend(chapters::save_chapters(
+            &chapters,
+            output_folder,
+            "chapters",
+        )?);
+    }
+
+    match cover::save_cover(&avformat_context, output_folder, "cover") {
+        Ok(path) => report.files.extend(path),
+        Err(error) => warn(report, observer, &format!("{}", error)),
+    }
+
+    let metadata_path = output_folder.join("metadata.json");
+    let mut metadata = metadata::MediaMetadata::read(&avformat_context, &chapters)?;
+    metadata.save(&metadata_path)?;
+    report.files.push(metadata_path.clone());
+
+    let loudness = match select_video_stream(&avformat_context, options)? {
+        Some(index) => extract_video(
+            &mut avformat_context,
+            index,
+            output_folder,
+            options,
+            observer,
+            interrupt,
+            report,
+        ),
+        None => extract_audio(
+            &mut avformat_context,
+            output_folder,
+            options,
+            observer,
+            interrupt,
+            report,
+        ),
+    }?;
+
+    // The measurements are only known after decoding, so the report is written again
+    if !loudness.is_empty() {
+        for (index, stream_loudness) in &loudness {
+            metadata.set_loudness(*index, stream_loudness);
+        }
+        metadata.save(&metadata_path)?;
+    }
+
+    Ok(())
+}
+
+fn select_video_stream(
+    avformat_context: &AVFormatContext,
+    options: &ExtractOptions,
+) -> anyhow::Result<Option<i32>> {
+    if let Some(index) = options.video_stream {
+        let stream = avformat_context
+            .stream(index)
+            .ok_or_else(|| format_err!("Could not find stream #{}", index))?;
+        if stream.codec_parameters()?.codec_type() != AVMediaType::Video {
+            return Err(format_err!("Stream #{} is not a video stream", index));
+        }
+        return Ok(Some(index));
+    }
+
+    // Cover art is exposed as a single frame video stream, which is never the main video
+    if let Some(stream) = avformat_context
+        .find_best_stream(AVMediaType::Video, None)
+        .ok()
+        .filter(|stream| !cover::is_attached_pic(stream))
+    {
+        return Ok(Some(stream.index()));
+    }
+
+    Ok(avformat_context
+        .streams()
+        .find(|stream| {
+            !cover::is_attached_pic(stream)
+                && stream.codec_parameters().map_or(false, |codec_parameters| {
+                    codec_parameters.codec_type() == AVMediaType::Video
+                })
+        })
+        .map(|stream| stream.index()))
+}
+
+/// Presentation time of a decoded frame, falling back to the decoder's guess for frames
+/// without pts. Frames without either can't be placed on the timeline.
+pub(crate) fn frame_timestamp(
+    frame: &AVFrame,
+    time_base: &Fraction,
+) -> anyhow::Result<Option<MediaTime>> {
+    match frame.pts().or_else(|| frame.best_effort_timestamp()) {
+        Some(pts) => Ok(Some(MediaTime::from_rational(pts, time_base)?)),
+        None => Ok(None),
+    }
+}
+
+fn extract_video(
+    avformat_context: &mut AVFormatContext,
+    index: i32,
+    output_folder: &Path,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+    interrupt: &cancel::Interrupt,
+    report: &mut ExtractReport,
+) -> anyhow::Result<Vec<(i32, loudness::Loudness)>> {
+    let format = options.image_output_format();
+    let flags = options.sws_flags();
+    let duration = avformat_context.duration();
+    // Frame timestamps include the start time, seek targets and the start offset don't
+    let start_time = avformat_context
+        .start_time()?
+        .unwrap_or_else(|| MediaTime::from_seconds(0));
 
     let spritesheet_path = output_folder.join("spritesheets");
     std::fs::create_dir_all(&spritesheet_path)?;
-    let mut spritesheet_manager = spritesheet::SpritesheetManager::new(
-        max_size,
-        num_horizontal,
-        num_vertical,
-        frame_interval,
+    let spritesheet_manager = spritesheet::SpritesheetManager::new(
+        options.max_size,
+        options.num_horizontal,
+        options.num_vertical,
+        options.frame_interval,
         spritesheet_path,
         "preview",
-        format,
+        format.clone(),
     );
 
-    let mut stream: AVStream = avformat_context
-        .streams()
-        .find(|stream| {
-            if let Ok(codec_parameters) = stream.codec_parameters() {
-                return codec_parameters.codec_type() == AVMediaType::Video;
-            }
+    // Seeking stops at the duration, so files without one are read to the end
+    let sampling = match (options.sampling, duration) {
+        (SamplingMode::Seek, None) => {
+            warn(
+                report,
+                observer,
+                "The duration is unknown, sampling frames sequentially",
+            );
+            SamplingMode::Sequential
+        }
+        (sampling, _) => sampling,
+    };
 
-            false
-        })
-        .ok_or_else(|| format_err!("Could not find video stream"))?;
-    stream.set_discard(AVDiscard::NonKey);
+    let mut caption_decoder = None;
+    if options.captions {
+        match sampling {
+            SamplingMode::Sequential => caption_decoder = Some(captions::CaptionDecoder::new()),
+            SamplingMode::Seek => warn(
+                report,
+                observer,
+                "Closed captions can only be extracted with sequential sampling",
+            ),
+        }
+    }
+    // Captions are carried by every frame, so all of them have to be decoded
+    let discard = match caption_decoder {
+        Some(_) => AVDiscard::Default,
+        None => AVDiscard::NonKey,
+    };
+
+    let mut stream = avformat_context
+        .stream(index)
+        .ok_or_else(|| format_err!("Could not find stream #{}", index))?;
+    stream.set_discard(discard);
 
     let index = stream.index();
     let time_base = stream.time_base();
@@ -54,88 +384,529 @@ pub fn extract(
     let codec_parameters = stream.codec_parameters()?;
     let local_codec = codec_parameters.find_decoder()?;
 
-    println!(
-        "Stream #{}, type: {:#?}, codec: {:#?}",
-        index,
-        codec_parameters.codec_type(),
-        local_codec.name()?
-    );
+    observer.stream_selected(index, &local_codec.name()?);
 
-    let mut output_frame =
-        AVFrame::new().map_err(|error| format_err!("Could not create output frame: {}", error))?;
+    let mut codec_context = AVCodecContext::new(&local_codec)
+        .map_err(|error| format_err!("Could not init codec context: {}", error))?;
+    codec_context.set_parameters(&codec_parameters);
+    codec_context
+        .open(&local_codec, None)
+        .map_err(|error| format_err!("Could not open codec: {}", error))?;
 
-    if codec_parameters.codec_type() == AVMediaType::Video {
-        let mut codec_context = AVCodecContext::new(&local_codec)
-            .map_err(|error| format_err!("Could not init codec context: {}", error))?;
-        codec_context.set_parameters(&codec_parameters);
-        codec_context.open(&local_codec);
+    codec_context.set_skip_loop_filter(discard);
+    codec_context.set_skip_idct(discard);
+    codec_context.set_skip_frame(discard);
 
-        codec_context.set_skip_loop_filter(AVDiscard::NonKey);
-        codec_context.set_skip_idct(AVDiscard::NonKey);
-        codec_context.set_skip_frame(AVDiscard::NonKey);
+    let mut packet = AVPacket::new()
+        .map_err(|error| format_err!("Could not init temporary packet: {}", error))?;
 
-        let mut packet = AVPacket::new()
-            .map_err(|error| format_err!("Could not init temporary packet: {}", error))?;
+    let mut frame =
+        AVFrame::new().map_err(|error| format_err!("Could not create input frame: {}", error))?;
 
-        let mut frame = AVFrame::new()
-            .map_err(|error| format_err!("Could not create input frame: {}", error))?;
+    let mut sampler = FrameSampler {
+        spritesheet_manager,
+        thumbnail_selector: thumbnail::ThumbnailSelector::new(
+            options.thumbnail_max_size,
+            duration,
+            options.scaler,
+            flags,
+        )?,
+        output_frame: AVFrame::new()
+            .map_err(|error| format_err!("Could not create output frame: {}", error))?,
+        scale_context: SwsContext::new(),
+        scaler: options.scaler,
+        flags,
+        frames_sampled: 0,
+        last_timestamp: None,
+    };
 
-        let mut scale_context = SwsContext::new();
+    let mut audio_analyzers = Vec::new();
+    if options.loudness {
+        match sampling {
+            SamplingMode::Sequential => {
+                audio_analyzers =
+                    open_audio_analyzers(avformat_context, None, options, observer, report)?;
+            }
+            SamplingMode::Seek => warn(
+                report,
+                observer,
+                "Loudness can only be measured with sequential sampling",
+            ),
+        }
+    }
 
-        while avformat_context.read_frame(&mut packet).is_ok() {
-            if packet.stream_index() == index {
-                codec_context
-                    .in_packet(&mut packet)
-                    .map_err(|error| format_err!("Could not load packet: {}", error))?;
-                while codec_context.out_frame(&mut frame).is_ok() {
-                    let timestamp = media_time::MediaTime::from_rational(frame.pts(), &time_base)?;
+    let mut subtitle_extractors = open_subtitle_extractors(avformat_context, observer, report)?;
+    if sampling == SamplingMode::Seek && !subtitle_extractors.is_empty() {
+        warn(
+            report,
+            observer,
+            "Subtitles can only be extracted with sequential sampling",
+        );
+        subtitle_extractors.clear();
+    }
 
-                    println!(
-                        "Frame {}: {} @ {}",
-                        frame.coded_picture_number(),
-                        timestamp,
-                        frame.key_frame()
-                    );
+    let mut last_timestamp = None;
+    match sampling {
+        SamplingMode::Sequential => {
+            while avformat_context.read_frame(&mut packet).is_ok() {
+                interrupt.check()?;
+                let stream_index = packet.stream_index();
+                if let Some(extractor) = subtitle_extractors
+                    .iter_mut()
+                    .find(|extractor| extractor.index() == stream_index)
+                {
+                    // A corrupt packet only loses its own cue
+                    if let Err(error) = extractor.decode(&mut packet) {
+                        warn(
+                            report,
+                            observer,
+                            &format!(
+                                "Skipping subtitle packet of stream #{}: {}",
+                                stream_index, error
+                            ),
+                        );
+                    }
+                } else if let Some(position) = audio_analyzers
+                    .iter()
+                    .position(|analyzer| analyzer.index() == stream_index)
+                {
+                    // Loudness is secondary, so a broken audio stream only loses its analysis
+                    if let Err(error) = audio_analyzers[position].decode(&mut packet) {
+                        warn(
+                            report,
+                            observer,
+                            &format!(
+                                "Could not analyze audio stream #{}: {}",
+                                stream_index, error
+                            ),
+                        );
+                        audio_analyzers.remove(position);
+                    }
+                } else if stream_index == index {
+                    codec_context
+                        .in_packet(&mut packet)
+                        .map_err(|error| format_err!("Could not load packet: {}", error))?;
+                    while codec_context.out_frame(&mut frame).is_ok() {
+                        report.frames_decoded += 1;
+                        let timestamp = match frame_timestamp(&frame, &time_base)? {
+                            Some(timestamp) => timestamp,
+                            None => continue,
+                        };
+                        observer.progress(timestamp, duration);
+                        last_timestamp = last_timestamp.max(Some(timestamp));
 
-                    if spritesheet_manager.fulfils_frame_interval(timestamp) {
-                        if !spritesheet_manager.initialized() {
-                            spritesheet_manager
-                                .initialize(frame.width() as u32, frame.height() as u32);
-                            output_frame
-                                .init(
-                                    spritesheet_manager.sprite_width() as i32,
-                                    spritesheet_manager.sprite_height() as i32,
-                                    AVPixelFormat::RGB24,
-                                )
-                                .map_err(|error| {
-                                    format_err!("Could not init output frame: {}", error)
-                                })?;
-                            scale_context
-                                .reinit(&frame, &output_frame, scaler, flags)
-                                .map_err(|error| {
-                                    format_err!("Could not reinit scale context: {}", error)
-                                })?;
+                        if let Some(decoder) = caption_decoder.as_mut() {
+                            if let Some(side_data) =
+                                frame.find_side_data(AVFrameSideDataType::A53ClosedCaptions)
+                            {
+                                decoder.decode(side_data.data(), timestamp);
+                            }
                         }
 
-                        scale_context.scale(&frame, &mut output_frame);
-
-                        spritesheet_manager.add_image(
-                            timestamp,
-                            image::ImageBuffer::from_raw(
-                                output_frame.width() as u32,
-                                output_frame.height() as u32,
-                                output_frame.data(0).to_vec(),
-                            )
-                            .ok_or_else(|| format_err!("Could not process frame"))?,
-                        )?;
+                        if timestamp - start_time >= options.start_offset
+                            && sampler
+                                .spritesheet_manager
+                                .fulfils_frame_interval(timestamp)
+                        {
+                            sampler.sample(&frame, timestamp, observer, report)?;
+                        }
                     }
                 }
             }
         }
+        SamplingMode::Seek => {
+            let mut target = options.start_offset;
+            while duration.is_some_and(|duration| target < duration) {
+                interrupt.check()?;
+                avformat_context
+                    .seek(target)
+                    .map_err(|error| format_err!("Could not seek to {}: {}", target, error))?;
+                codec_context.flush_buffers();
+
+                if !decode_next_frame(
+                    avformat_context,
+                    &mut codec_context,
+                    &mut packet,
+                    &mut frame,
+                    index,
+                )? {
+                    break;
+                }
+                report.frames_decoded += 1;
+
+                let timestamp = match frame_timestamp(&frame, &time_base)? {
+                    Some(timestamp) => timestamp,
+                    None => {
+                        target = target + options.frame_interval;
+                        continue;
+                    }
+                };
+                observer.progress(timestamp, duration);
+
+                // Sparse keyframes can make several seeks land on the same frame, which the
+                // sampler only takes once
+                sampler.sample(&frame, timestamp, observer, report)?;
+
+                target = std::cmp::max(target, timestamp - start_time) + options.frame_interval;
+            }
+        }
+    }
+    // An interrupted read looks like the end of the file, so check before writing outputs
+    interrupt.check()?;
+    // Frame and cue timestamps include the start time, without a known duration the outputs
+    // end with the last decoded frame
+    let end = duration
+        .map(|duration| start_time + duration)
+        .or(last_timestamp)
+        .unwrap_or_else(|| MediaTime::from_seconds(0));
+
+    let mut loudness = Vec::new();
+    for analyzer in audio_analyzers {
+        if let Some(analysis) = analyzer.finish()? {
+            loudness.push((analysis.index, analysis.loudness));
+        }
+    }
+    save_subtitles(
+        subtitle_extractors,
+        output_folder,
+        end,
+        options,
+        observer,
+        report,
+    )?;
+    if let Some(decoder) = caption_decoder {
+        match decoder.save(output_folder, "captions", end)? {
+            Some((path, track)) => {
+                report.files.push(path.clone());
+                segment_track(&path, &track, end, options, report)?;
+            }
+            None => warn(
+                report,
+                observer,
+                "The video stream contains no closed captions",
+            ),
+        }
+    }
 
-        spritesheet_manager.end_frame(duration);
-        spritesheet_manager.save()?;
+    if sampler.frames_sampled == 0 {
+        warn(
+            report,
+            observer,
+            "No frames could be sampled from the video stream",
+        );
+        return Ok(loudness);
+    }
+
+    sampler.spritesheet_manager.end_frame(end)?;
+    let written = sampler.spritesheet_manager.files().len();
+    sampler.spritesheet_manager.save()?;
+    sampler.collect_sheets(written, observer, report);
+    segment_track(
+        &sampler.spritesheet_manager.metadata_path(),
+        sampler.spritesheet_manager.track(),
+        end,
+        options,
+        report,
+    )?;
+    report.sprites = sampler.spritesheet_manager.geometry();
+    report.frames_sampled = sampler.frames_sampled;
+
+    if sampler.thumbnail_selector.is_rejected() {
+        warn(
+            report,
+            observer,
+            "No frame passed the thumbnail quality checks, using the best rejected frame",
+        );
+    }
+    report.files.extend(
+        sampler
+            .thumbnail_selector
+            .save(output_folder, "thumbnail", format)?,
+    );
+
+    Ok(loudness)
+}
+
+fn extract_audio(
+    avformat_context: &mut AVFormatContext,
+    output_folder: &Path,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+    interrupt: &cancel::Interrupt,
+    report: &mut ExtractReport,
+) -> anyhow::Result<Vec<(i32, loudness::Loudness)>> {
+    let duration = avformat_context.duration();
+    let start_time = avformat_context
+        .start_time()?
+        .unwrap_or_else(|| MediaTime::from_seconds(0));
+
+    let index = avformat_context
+        .find_best_stream(AVMediaType::Audio, None)
+        .map_err(|error| format_err!("Could not find video or audio stream: {}", error))?
+        .index();
+
+    let mut analyzers =
+        open_audio_analyzers(avformat_context, Some(index), options, observer, report)?;
+    if let Some(analyzer) = analyzers.iter().find(|analyzer| analyzer.index() == index) {
+        observer.stream_selected(index, analyzer.codec_name());
+    }
+
+    let mut subtitle_extractors = open_subtitle_extractors(avformat_context, observer, report)?;
+
+    let mut last_timestamp = None;
+    let mut packet = AVPacket::new()
+        .map_err(|error| format_err!("Could not init temporary packet: {}", error))?;
+
+    while avformat_context.read_frame(&mut packet).is_ok() {
+        interrupt.check()?;
+        let stream_index = packet.stream_index();
+        if let Some(extractor) = subtitle_extractors
+            .iter_mut()
+            .find(|extractor| extractor.index() == stream_index)
+        {
+            // A corrupt packet only loses its own cue
+            if let Err(error) = extractor.decode(&mut packet) {
+                warn(
+                    report,
+                    observer,
+                    &format!(
+                        "Skipping subtitle packet of stream #{}: {}",
+                        stream_index, error
+                    ),
+                );
+            }
+        } else if let Some(position) = analyzers
+            .iter()
+            .position(|analyzer| analyzer.index() == stream_index)
+        {
+            match analyzers[position].decode(&mut packet) {
+                Ok(Some(timestamp)) if stream_index == index => {
+                    observer.progress(timestamp, duration);
+                    last_timestamp = last_timestamp.max(Some(timestamp));
+                }
+                Ok(_) => {}
+                Err(error) if stream_index != index => {
+                    warn(
+                        report,
+                        observer,
+                        &format!(
+                            "Could not analyze audio stream #{}: {}",
+                            stream_index, error
+                        ),
+                    );
+                    analyzers.remove(position);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    interrupt.check()?;
+    // Subtitle timestamps include the start time, without a known duration the subtitles end
+    // with the last decoded frame
+    let end = duration
+        .map(|duration| start_time + duration)
+        .or(last_timestamp)
+        .unwrap_or_else(|| MediaTime::from_seconds(0));
+
+    save_subtitles(
+        subtitle_extractors,
+        output_folder,
+        end,
+        options,
+        observer,
+        report,
+    )?;
+
+    let mut loudness = Vec::new();
+    let mut waveform = None;
+    for analyzer in analyzers {
+        if analyzer.index() == index {
+            report.frames_decoded = analyzer.frames_decoded();
+        }
+        if let Some(analysis) = analyzer.finish()? {
+            loudness.push((analysis.index, analysis.loudness));
+            waveform = waveform.or(analysis.waveform);
+        }
+    }
+
+    let waveform = match waveform {
+        Some(waveform) => waveform,
+        None => {
+            warn(
+                report,
+                observer,
+                "No frames could be decoded from the audio stream",
+            );
+            return Ok(loudness);
+        }
+    };
+
+    let dat_path = output_folder.join("waveform.dat");
+    waveform.save_dat(&dat_path)?;
+    report.files.push(dat_path);
+
+    let json_path = output_folder.join("waveform.json");
+    waveform.save_json(&json_path)?;
+    report.files.push(json_path);
+
+    if let Some((width, height)) = options.waveform_image_size {
+        let image_path = output_folder.join("waveform.png");
+        waveform
+            .render(width, height)
+            .save_with_format(&image_path, image::ImageFormat::Png)
+            .map_err(|error| format_err!("Could not write waveform image: {}", error))?;
+        report.files.push(image_path);
+    }
+
+    Ok(loudness)
+}
+
+/// Opens all audio streams, only failing on the primary one which also gets a waveform
+fn open_audio_analyzers(
+    avformat_context: &AVFormatContext,
+    primary: Option<i32>,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+    report: &mut ExtractReport,
+) -> anyhow::Result<Vec<audio::AudioAnalyzer>> {
+    let mut analyzers = Vec::new();
+    for stream in avformat_context.streams() {
+        if stream.codec_parameters()?.codec_type() != AVMediaType::Audio {
+            continue;
+        }
+
+        let index = stream.index();
+        let is_primary = primary == Some(index);
+        let waveform = Some(options.waveform_samples_per_pixel).filter(|_| is_primary);
+        match audio::AudioAnalyzer::new(&stream, waveform) {
+            Ok(analyzer) => analyzers.push(analyzer),
+            Err(error) if !is_primary => warn(
+                report,
+                observer,
+                &format!("Could not analyze audio stream #{}: {}", index, error),
+            ),
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(analyzers)
+}
+
+/// Opens all text subtitle streams, bitmap subtitles can't be converted to WebVTT
+fn open_subtitle_extractors(
+    avformat_context: &AVFormatContext,
+    observer: &mut dyn ExtractObserver,
+    report: &mut ExtractReport,
+) -> anyhow::Result<Vec<subtitles::SubtitleExtractor>> {
+    let mut extractors = Vec::new();
+    for stream in avformat_context.streams() {
+        let codec_parameters = stream.codec_parameters()?;
+        if codec_parameters.codec_type() != AVMediaType::Subtitle {
+            continue;
+        }
+
+        let index = stream.index();
+        if !codec_parameters.is_text_subtitle() {
+            warn(
+                report,
+                observer,
+                &format!("Skipping bitmap subtitle stream #{}", index),
+            );
+            continue;
+        }
+        match subtitles::SubtitleExtractor::new(&stream) {
+            Ok(extractor) => extractors.push(extractor),
+            Err(error) => warn(
+                report,
+                observer,
+                &format!("Could not extract subtitle stream #{}: {}", index, error),
+            ),
+        }
+    }
+
+    Ok(extractors)
+}
+
+fn save_subtitles(
+    extractors: Vec<subtitles::SubtitleExtractor>,
+    output_folder: &Path,
+    end: MediaTime,
+    options: &ExtractOptions,
+    observer: &mut dyn ExtractObserver,
+    report: &mut ExtractReport,
+) -> anyhow::Result<()> {
+    let subtitle_path = output_folder.join("subtitles");
+    for extractor in extractors {
+        if extractor.is_empty() {
+            warn(
+                report,
+                observer,
+                &format!("Subtitle stream #{} contains no text", extractor.index()),
+            );
+            continue;
+        }
+
+        std::fs::create_dir_all(&subtitle_path)?;
+        let (mut subtitle_track, track) = extractor.save(&subtitle_path, end)?;
+        report.files.push(subtitle_track.path.clone());
+        subtitle_track.playlist =
+            segment_track(&subtitle_track.path, &track, end, options, report)?;
+        report.subtitles.push(subtitle_track);
     }
 
     Ok(())
 }
+
+/// Splits a track written to `path` into HLS segments next to it if enabled, returns the
+/// playlist
+fn segment_track(
+    path: &Path,
+    track: &WebVTTFile,
+    duration: MediaTime,
+    options: &ExtractOptions,
+    report: &mut ExtractReport,
+) -> anyhow::Result<Option<PathBuf>> {
+    let segment_duration = match options.hls_segment_duration {
+        Some(segment_duration) => segment_duration,
+        None => return Ok(None),
+    };
+
+    let name = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format_err!("Invalid track path: {}", path.display()))?;
+    let directory = path
+        .parent()
+        .ok_or_else(|| format_err!("Invalid track path: {}", path.display()))?;
+
+    let paths = HlsSegmenter::new(segment_duration)
+        .map_err(|error| format_err!("Could not write HLS segments: {}", error))?
+        .with_mpegts_offset(options.hls_mpegts_offset)
+        .save(track, duration, directory, name)
+        .map_err(|error| format_err!("Could not write HLS segments: {}", error))?;
+    let playlist = paths.last().cloned();
+    report.files.extend(paths);
+
+    Ok(playlist)
+}
+
+fn decode_next_frame(
+    avformat_context: &mut AVFormatContext,
+    codec_context: &mut AVCodecContext,
+    packet: &mut AVPacket,
+    frame: &mut AVFrame,
+    index: i32,
+) -> anyhow::Result<bool> {
+    while avformat_context.read_frame(packet).is_ok() {
+        if packet.stream_index() == index {
+            codec_context
+                .in_packet(packet)
+                .map_err(|error| format_err!("Could not load packet: {}", error))?;
+            if codec_context.out_frame(frame).is_ok() {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
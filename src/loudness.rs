@@ -0,0 +1,302 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use ffmpeg_api::enums::AVChannelLayout;
+
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Loudness the ReplayGain 2.0 track gain normalizes to
+pub const REPLAYGAIN_REFERENCE: f64 = -18.0;
+
+// Gating blocks are built from 100ms sub-blocks: 400ms momentary blocks with 75% overlap,
+// and 3s short-term blocks for the loudness range
+const SUB_BLOCKS_PER_SECOND: u32 = 10;
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+const TRUE_PEAK_FACTOR: usize = 4;
+const TRUE_PEAK_TAPS: usize = 12;
+
+#[derive(Debug, Copy, Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Biquad { b, a, z: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+/// The BS.1770 pre-filter (high shelf) and RLB filter (high pass), derived for any sample rate
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Estimates inter-sample peaks by 4x oversampling with a windowed sinc interpolator
+#[derive(Debug, Clone)]
+struct TruePeak {
+    phases: [[f64; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR],
+    history: [f64; TRUE_PEAK_TAPS],
+}
+
+impl TruePeak {
+    fn new() -> Self {
+        let length = TRUE_PEAK_FACTOR * TRUE_PEAK_TAPS;
+        let center = (length - 1) as f64 / 2.0;
+        let mut phases = [[0.0; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR];
+        for n in 0..length {
+            let x = (n as f64 - center) / TRUE_PEAK_FACTOR as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / (length - 1) as f64).cos();
+            phases[n % TRUE_PEAK_FACTOR][n / TRUE_PEAK_FACTOR] = sinc * window;
+        }
+
+        TruePeak {
+            phases,
+            history: [0.0; TRUE_PEAK_TAPS],
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.history.copy_within(0..TRUE_PEAK_TAPS - 1, 1);
+        self.history[0] = sample;
+
+        self.phases
+            .iter()
+            .map(|phase| {
+                phase
+                    .iter()
+                    .zip(self.history.iter())
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum::<f64>()
+                    .abs()
+            })
+            .fold(sample.abs(), f64::max)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, absent for silence
+    pub integrated: Option<f64>,
+    /// Loudness range in LU, absent if too short or silent
+    pub range: Option<f64>,
+    /// Linear true peak, relative to full scale
+    pub true_peak: f64,
+}
+
+impl Loudness {
+    pub fn true_peak_db(&self) -> Option<f64> {
+        if self.true_peak > 0.0 {
+            Some(20.0 * self.true_peak.log10())
+        } else {
+            None
+        }
+    }
+
+    pub fn replaygain_gain(&self) -> Option<f64> {
+        self.integrated
+            .map(|integrated| REPLAYGAIN_REFERENCE - integrated)
+    }
+}
+
+/// Weights per channel in interleaved order: surround channels are boosted, LFE is ignored
+pub fn channel_weights(layout: AVChannelLayout) -> Vec<f64> {
+    (0..64)
+        .map(|bit| AVChannelLayout::from_bits_truncate(1 << bit))
+        .filter(|channel| !channel.is_empty() && layout.contains(*channel))
+        .map(|channel| {
+            if channel == AVChannelLayout::LOW_FREQUENCY
+                || channel == AVChannelLayout::LOW_FREQUENCY_2
+            {
+                0.0
+            } else if channel == AVChannelLayout::BACK_LEFT
+                || channel == AVChannelLayout::BACK_RIGHT
+                || channel == AVChannelLayout::SIDE_LEFT
+                || channel == AVChannelLayout::SIDE_RIGHT
+            {
+                1.41
+            } else {
+                1.0
+            }
+        })
+        .collect()
+}
+
+/// Measures loudness according to ITU-R BS.1770-4 and EBU R128 (Tech 3341/3342)
+pub struct LoudnessMeter {
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    true_peaks: Vec<TruePeak>,
+    sub_block_size: usize,
+    sub_block_fill: usize,
+    sub_block_energy: f64,
+    recent_sub_blocks: VecDeque<f64>,
+    block_energies: Vec<f64>,
+    short_term_energies: Vec<f64>,
+    true_peak: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, weights: Vec<f64>) -> Self {
+        let channels = weights.len();
+        LoudnessMeter {
+            weights,
+            filters: vec![k_weighting(sample_rate as f64); channels],
+            true_peaks: vec![TruePeak::new(); channels],
+            sub_block_size: (sample_rate / SUB_BLOCKS_PER_SECOND).max(1) as usize,
+            sub_block_fill: 0,
+            sub_block_energy: 0.0,
+            recent_sub_blocks: VecDeque::with_capacity(SHORT_TERM_SUB_BLOCKS),
+            block_energies: Vec::new(),
+            short_term_energies: Vec::new(),
+            true_peak: 0.0,
+        }
+    }
+
+    /// Adds interleaved samples, trailing incomplete frames are ignored
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        let channels = self.weights.len();
+        if channels == 0 {
+            return;
+        }
+
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let sample = sample as f64;
+                self.true_peak = self.true_peak.max(self.true_peaks[channel].process(sample));
+
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let filtered = high_pass.process(shelf.process(sample));
+                self.sub_block_energy += self.weights[channel] * filtered * filtered;
+            }
+
+            self.sub_block_fill += 1;
+            if self.sub_block_fill == self.sub_block_size {
+                self.end_sub_block();
+            }
+        }
+    }
+
+    fn end_sub_block(&mut self) {
+        if self.recent_sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            self.recent_sub_blocks.pop_front();
+        }
+        self.recent_sub_blocks.push_back(self.sub_block_energy);
+        self.sub_block_energy = 0.0;
+        self.sub_block_fill = 0;
+
+        if self.recent_sub_blocks.len() >= MOMENTARY_SUB_BLOCKS {
+            let energy: f64 = self
+                .recent_sub_blocks
+                .iter()
+                .rev()
+                .take(MOMENTARY_SUB_BLOCKS)
+                .sum();
+            self.block_energies
+                .push(energy / (MOMENTARY_SUB_BLOCKS * self.sub_block_size) as f64);
+        }
+        if self.recent_sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            let energy: f64 = self.recent_sub_blocks.iter().sum();
+            self.short_term_energies
+                .push(energy / (SHORT_TERM_SUB_BLOCKS * self.sub_block_size) as f64);
+        }
+    }
+
+    pub fn finish(&self) -> Loudness {
+        Loudness {
+            integrated: integrated_loudness(&self.block_energies),
+            range: loudness_range(&self.short_term_energies),
+            true_peak: self.true_peak,
+        }
+    }
+}
+
+fn to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn mean(energies: &[f64]) -> f64 {
+    energies.iter().sum::<f64>() / energies.len() as f64
+}
+
+/// Drops blocks below the absolute gate and below the mean of the remaining ones plus `offset`
+fn gate(energies: &[f64], offset: f64) -> Vec<f64> {
+    let absolute: Vec<f64> = energies
+        .iter()
+        .copied()
+        .filter(|&energy| to_loudness(energy) > ABSOLUTE_GATE)
+        .collect();
+    if absolute.is_empty() {
+        return absolute;
+    }
+
+    let relative = to_loudness(mean(&absolute)) + offset;
+    absolute
+        .into_iter()
+        .filter(|&energy| to_loudness(energy) > relative)
+        .collect()
+}
+
+fn integrated_loudness(block_energies: &[f64]) -> Option<f64> {
+    let gated = gate(block_energies, RELATIVE_GATE);
+    if gated.is_empty() {
+        return None;
+    }
+
+    Some(to_loudness(mean(&gated)))
+}
+
+fn loudness_range(short_term_energies: &[f64]) -> Option<f64> {
+    let mut loudness: Vec<f64> = gate(short_term_energies, RANGE_RELATIVE_GATE)
+        .into_iter()
+        .map(to_loudness)
+        .collect();
+    if loudness.is_empty() {
+        return None;
+    }
+    loudness.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+    Some(percentile(0.95) - percentile(0.10))
+}
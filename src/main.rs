@@ -1,24 +1,40 @@
 use std::path::Path;
+use std::time::Duration;
 
-use ffmpeg_api::enums::{SwsFlags, SwsScaler};
-use image::ImageOutputFormat;
+use ffmpeg_api::enums::{AVLogLevel, SwsScaler};
+use media_ingestion::observer::{ExtractObserver, JsonLinesObserver, NoopObserver};
+use media_ingestion::options::{
+    parse_image_format, parse_image_size, parse_sampling_mode, parse_scaler,
+};
+use media_ingestion::spritesheet::ImageFormat;
+use media_ingestion::{ExtractOptions, SamplingMode};
 use media_time::MediaTime;
 use structopt::StructOpt;
 
-fn parse_scaler(src: &str) -> Result<SwsScaler, String> {
+fn parse_ffmpeg_log_level(src: &str) -> Result<AVLogLevel, String> {
     match src {
-        "fast_bilinear" => Ok(SwsScaler::FastBilinear),
-        "bilinear" => Ok(SwsScaler::Bilinear),
-        "bicubic" => Ok(SwsScaler::Bicubic),
-        "x" => Ok(SwsScaler::X),
-        "point" => Ok(SwsScaler::Point),
-        "area" => Ok(SwsScaler::Area),
-        "bicublin" => Ok(SwsScaler::Bicublin),
-        "gauss" => Ok(SwsScaler::Gauss),
-        "sinc" => Ok(SwsScaler::Sinc),
-        "lanczos" => Ok(SwsScaler::Lanczos),
-        "spline" => Ok(SwsScaler::Spline),
-        _ => Err(format!("Invalid scaler: {}", src)),
+        "quiet" => Ok(AVLogLevel::Quiet),
+        "panic" => Ok(AVLogLevel::Panic),
+        "fatal" => Ok(AVLogLevel::Fatal),
+        "error" => Ok(AVLogLevel::Error),
+        "warning" => Ok(AVLogLevel::Warning),
+        "info" => Ok(AVLogLevel::Info),
+        "verbose" => Ok(AVLogLevel::Verbose),
+        "debug" => Ok(AVLogLevel::Debug),
+        "trace" => Ok(AVLogLevel::Trace),
+        _ => Err(format!("Invalid log level: {}", src)),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ProgressFormat {
+    Json,
+}
+
+fn parse_progress_format(src: &str) -> Result<ProgressFormat, String> {
+    match src {
+        "json" => Ok(ProgressFormat::Json),
+        _ => Err(format!("Invalid progress format: {}", src)),
     }
 }
 
@@ -28,19 +44,45 @@ struct Options {
     input: String,
     output: String,
     #[structopt(long = "frame-interval", default_value = "2")]
-    frame_interval: i64,
+    frame_interval: MediaTime,
+    #[structopt(long = "start-offset", default_value = "0")]
+    start_offset: MediaTime,
     #[structopt(long = "num-horizontal", default_value = "5")]
     num_horizontal: u32,
     #[structopt(long = "num-vertical", default_value = "5")]
     num_vertical: u32,
     #[structopt(long = "max-size", default_value = "240")]
     max_size: u32,
-    #[structopt(long = "format", default_value = "jpg")]
-    format: String,
+    #[structopt(long = "format", default_value = "jpg", parse(try_from_str = parse_image_format))]
+    format: ImageFormat,
     #[structopt(long = "quality", default_value = "90")]
     quality: u8,
     #[structopt(long = "scaler", default_value = "area", parse(try_from_str = parse_scaler))]
     scaler: SwsScaler,
+    #[structopt(long = "sampling", default_value = "sequential", parse(try_from_str = parse_sampling_mode))]
+    sampling: SamplingMode,
+    #[structopt(long = "video-stream")]
+    video_stream: Option<i32>,
+    #[structopt(long = "thumb-max-size")]
+    thumb_max_size: Option<u32>,
+    #[structopt(long = "waveform-samples-per-pixel", default_value = "256")]
+    waveform_samples_per_pixel: u32,
+    #[structopt(long = "waveform-image", parse(try_from_str = parse_image_size))]
+    waveform_image: Option<(u32, u32)>,
+    #[structopt(long = "loudness")]
+    loudness: bool,
+    #[structopt(long = "captions")]
+    captions: bool,
+    #[structopt(long = "hls-segment-duration")]
+    hls_segment_duration: Option<MediaTime>,
+    #[structopt(long = "hls-mpegts-offset", default_value = "0")]
+    hls_mpegts_offset: u64,
+    #[structopt(long = "timeout")]
+    timeout: Option<u64>,
+    #[structopt(long = "progress", parse(try_from_str = parse_progress_format))]
+    progress: Option<ProgressFormat>,
+    #[structopt(long = "ffmpeg-log-level", default_value = "warning", parse(try_from_str = parse_ffmpeg_log_level))]
+    ffmpeg_log_level: AVLogLevel,
     #[structopt(long = "fast-chroma")]
     fast_chroma: bool,
     #[structopt(long = "fast-rounding")]
@@ -52,34 +94,57 @@ struct Options {
 fn main() -> anyhow::Result<()> {
     let options = Options::from_args();
 
-    let mut flags = SwsFlags::empty();
-    if !options.fast_chroma {
-        flags |= SwsFlags::FULL_CHROMA_INTERPOLATION | SwsFlags::FULL_CHROMA_INPUT;
-    }
-    if !options.fast_rounding {
-        flags |= SwsFlags::ACCURATE_ROUNDING;
-    }
-    if !options.fast_scaling {
-        flags |= SwsFlags::BIT_EXACT_SCALING;
-    }
+    // FFmpeg messages are logged with the target "ffmpeg::<component>", RUST_LOG takes precedence
+    env_logger::from_env(env_logger::Env::default().default_filter_or(format!(
+        "warn,ffmpeg={}",
+        ffmpeg_api::log::level_filter(options.ffmpeg_log_level)
+    )))
+    .init();
+    ffmpeg_api::log::set_level(options.ffmpeg_log_level);
+    ffmpeg_api::log::init();
+
+    let extract_options = ExtractOptions::new()
+        .max_size(options.max_size)
+        .num_horizontal(options.num_horizontal)
+        .num_vertical(options.num_vertical)
+        .frame_interval(options.frame_interval)?
+        .start_offset(options.start_offset)
+        .format(options.format)
+        .quality(options.quality)
+        .scaler(options.scaler)
+        .sampling(options.sampling)
+        .video_stream(options.video_stream)
+        .thumbnail_max_size(options.thumb_max_size)
+        .waveform_samples_per_pixel(options.waveform_samples_per_pixel)
+        .waveform_image_size(options.waveform_image)
+        .loudness(options.loudness)
+        .captions(options.captions)
+        .hls_segment_duration(options.hls_segment_duration)?
+        .hls_mpegts_offset(options.hls_mpegts_offset)
+        .fast_chroma(options.fast_chroma)
+        .fast_rounding(options.fast_rounding)
+        .fast_scaling(options.fast_scaling)
+        .timeout(options.timeout.map(Duration::from_secs));
+
+    let mut observer: Box<dyn ExtractObserver> = match options.progress {
+        Some(ProgressFormat::Json) => Box::new(JsonLinesObserver::new(std::io::stderr())),
+        None => Box::new(NoopObserver),
+    };
 
-    if let Err(err) = media_ingestion::extract(
-        options.max_size,
-        options.num_horizontal,
-        options.num_vertical,
-        MediaTime::from_seconds(options.frame_interval),
+    match media_ingestion::extract_with_observer(
         Path::new(&options.input),
         Path::new(&options.output),
-        match options.format.as_str() {
-            "jpeg" | "jpg" => ImageOutputFormat::Jpeg(options.quality),
-            "png" => ImageOutputFormat::Png,
-            "bmp" => ImageOutputFormat::Bmp,
-            _ => panic!("Unsupported image format: {}", options.format),
-        },
-        options.scaler,
-        flags,
+        &extract_options,
+        observer.as_mut(),
     ) {
-        eprintln!("Error: {}", err)
+        Ok(report) => {
+            if options.progress.is_none() {
+                for warning in report.warnings {
+                    eprintln!("Warning: {}", warning)
+                }
+            }
+        }
+        Err(err) => eprintln!("Error: {}", err),
     }
 
     Ok(())
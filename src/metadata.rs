@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{format_err, Error};
+use ffmpeg_api::api::{AVCodecParameters, AVDictionary, AVFormatContext, AVStream};
+use ffmpeg_api::enums::{AVDisposition, AVMediaType};
+use fraction::Fraction;
+use media_time::MediaTime;
+use serde::{Deserialize, Serialize};
+
+use crate::chapters::Chapter;
+use crate::loudness::Loudness;
+
+/// Version of the metadata schema, incremented on every incompatible change.
+/// Fields may be added without a version bump, so readers should ignore unknown fields.
+pub const METADATA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub version: u32,
+    pub format: FormatMetadata,
+    #[serde(default, with = "crate::seconds::option")]
+    pub duration: Option<MediaTime>,
+    #[serde(default)]
+    pub bit_rate: Option<i64>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    #[serde(default)]
+    pub streams: Vec<StreamMetadata>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub long_name: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaType {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+impl From<AVMediaType> for MediaType {
+    fn from(media_type: AVMediaType) -> Self {
+        match media_type {
+            AVMediaType::Video => MediaType::Video,
+            AVMediaType::Audio => MediaType::Audio,
+            AVMediaType::Subtitle => MediaType::Subtitle,
+            AVMediaType::Data => MediaType::Data,
+            AVMediaType::Attachment => MediaType::Attachment,
+            AVMediaType::Unknown => MediaType::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rational {
+    pub num: u64,
+    pub den: u64,
+}
+
+impl Rational {
+    fn from_fraction(fraction: Fraction) -> Option<Rational> {
+        match (fraction.numer(), fraction.denom()) {
+            (Some(&num), Some(&den)) if num != 0 && den != 0 => Some(Rational { num, den }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamMetadata {
+    pub index: i32,
+    pub media_type: MediaType,
+    pub codec: String,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub bit_rate: Option<i64>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub disposition: Vec<String>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video: Option<VideoMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub frame_rate: Option<Rational>,
+    #[serde(default)]
+    pub sample_aspect_ratio: Option<Rational>,
+    #[serde(default)]
+    pub display_aspect_ratio: Option<Rational>,
+    #[serde(default)]
+    pub pixel_format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioMetadata {
+    pub sample_rate: u32,
+    pub channels: u32,
+    #[serde(default)]
+    pub sample_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<LoudnessMetadata>,
+}
+
+/// EBU R128 measurements, with the derived ReplayGain 2.0 track values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessMetadata {
+    /// Integrated loudness in LUFS
+    #[serde(default)]
+    pub integrated_loudness: Option<f64>,
+    /// Loudness range in LU
+    #[serde(default)]
+    pub loudness_range: Option<f64>,
+    /// True peak in dBTP
+    #[serde(default)]
+    pub true_peak: Option<f64>,
+    /// Gain in dB to reach the ReplayGain reference level of -18 LUFS
+    #[serde(default)]
+    pub replaygain_track_gain: Option<f64>,
+    /// Linear true peak, as used by ReplayGain
+    pub replaygain_track_peak: f64,
+}
+
+impl From<&Loudness> for LoudnessMetadata {
+    fn from(loudness: &Loudness) -> Self {
+        LoudnessMetadata {
+            integrated_loudness: loudness.integrated.map(round),
+            loudness_range: loudness.range.map(round),
+            true_peak: loudness.true_peak_db().map(round),
+            replaygain_track_gain: loudness.replaygain_gain().map(round),
+            replaygain_track_peak: (loudness.true_peak * 1_000_000.0).round() / 1_000_000.0,
+        }
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+impl MediaMetadata {
+    pub fn read(avformat_context: &AVFormatContext, chapters: &[Chapter]) -> Result<Self, Error> {
+        let input_format = avformat_context.input_format()?;
+
+        let streams = avformat_context
+            .streams()
+            .map(|stream| StreamMetadata::read(&stream))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Cover art is a video stream too, but doesn't make the file a video
+        let video = streams.iter().find(|stream| {
+            stream.media_type == MediaType::Video
+                && !stream.disposition.iter().any(|name| name == "attached_pic")
+        });
+        let mime_type = match video {
+            Some(stream) => input_format.determine_mime(&stream.codec).ok(),
+            None => streams
+                .iter()
+                .find(|stream| stream.media_type == MediaType::Audio)
+                .and_then(|stream| input_format.determine_mime(&stream.codec).ok()),
+        }
+        .map(String::from);
+
+        Ok(MediaMetadata {
+            version: METADATA_VERSION,
+            format: FormatMetadata {
+                name: input_format.name()?,
+                long_name: input_format.long_name().ok(),
+            },
+            duration: avformat_context
+                .duration()
+                .filter(|duration| *duration >= MediaTime::from_millis(0)),
+            bit_rate: Some(avformat_context.bit_rate()).filter(|bit_rate| *bit_rate > 0),
+            mime_type,
+            tags: tags(&avformat_context.metadata()),
+            streams,
+            chapters: chapters.to_vec(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let file = File::create(path.as_ref())
+            .map_err(|error| format_err!("Could not create metadata report: {}", error))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .map_err(|error| format_err!("Could not write metadata report: {}", error))?;
+        Ok(())
+    }
+
+    pub fn set_loudness(&mut self, index: i32, loudness: &Loudness) {
+        if let Some(audio) = self
+            .streams
+            .iter_mut()
+            .find(|stream| stream.index == index)
+            .and_then(|stream| stream.audio.as_mut())
+        {
+            audio.loudness = Some(LoudnessMetadata::from(loudness));
+        }
+    }
+}
+
+impl StreamMetadata {
+    fn read(stream: &AVStream) -> Result<Self, Error> {
+        let codec_parameters = stream.codec_parameters()?;
+        let media_type = MediaType::from(codec_parameters.codec_type());
+        let metadata = stream.metadata();
+
+        Ok(StreamMetadata {
+            index: stream.index(),
+            media_type,
+            codec: codec_parameters.codec_name()?,
+            profile: codec_parameters.profile_name(),
+            bit_rate: Some(codec_parameters.bit_rate()).filter(|bit_rate| *bit_rate > 0),
+            language: metadata
+                .get("language")
+                .filter(|language| language != "und"),
+            disposition: disposition_names(stream.disposition()),
+            tags: tags(&metadata),
+            video: match media_type {
+                MediaType::Video => Some(VideoMetadata::read(stream, &codec_parameters)),
+                _ => None,
+            },
+            audio: match media_type {
+                MediaType::Audio => Some(AudioMetadata::read(&codec_parameters)),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl VideoMetadata {
+    fn read(stream: &AVStream, codec_parameters: &AVCodecParameters) -> Self {
+        let width = codec_parameters.width().max(0) as u32;
+        let height = codec_parameters.height().max(0) as u32;
+
+        // The container's aspect ratio takes precedence over the one signalled in the bitstream
+        let sample_aspect_ratio = Rational::from_fraction(stream.sample_aspect_ratio())
+            .or_else(|| Rational::from_fraction(codec_parameters.sample_aspect_ratio()));
+        let display_aspect_ratio = if width != 0 && height != 0 {
+            let sample_aspect_ratio = sample_aspect_ratio.unwrap_or(Rational { num: 1, den: 1 });
+            Rational::from_fraction(Fraction::new(
+                width as u64 * sample_aspect_ratio.num,
+                height as u64 * sample_aspect_ratio.den,
+            ))
+        } else {
+            None
+        };
+
+        VideoMetadata {
+            width,
+            height,
+            frame_rate: Rational::from_fraction(stream.avg_frame_rate())
+                .or_else(|| Rational::from_fraction(stream.real_frame_rate())),
+            sample_aspect_ratio,
+            display_aspect_ratio,
+            pixel_format: codec_parameters.format_name(),
+        }
+    }
+}
+
+impl AudioMetadata {
+    fn read(codec_parameters: &AVCodecParameters) -> Self {
+        AudioMetadata {
+            sample_rate: codec_parameters.sample_rate().max(0) as u32,
+            channels: codec_parameters.channels().max(0) as u32,
+            sample_format: codec_parameters.format_name(),
+            loudness: None,
+        }
+    }
+}
+
+fn tags(dictionary: &AVDictionary) -> BTreeMap<String, String> {
+    dictionary.iter().collect()
+}
+
+fn disposition_names(disposition: AVDisposition) -> Vec<String> {
+    [
+        (AVDisposition::DEFAULT, "default"),
+        (AVDisposition::DUB, "dub"),
+        (AVDisposition::ORIGINAL, "original"),
+        (AVDisposition::COMMENT, "comment"),
+        (AVDisposition::LYRICS, "lyrics"),
+        (AVDisposition::KARAOKE, "karaoke"),
+        (AVDisposition::FORCED, "forced"),
+        (AVDisposition::HEARING_IMPAIRED, "hearing_impaired"),
+        (AVDisposition::VISUAL_IMPAIRED, "visual_impaired"),
+        (AVDisposition::CLEAN_EFFECTS, "clean_effects"),
+        (AVDisposition::ATTACHED_PIC, "attached_pic"),
+        (AVDisposition::TIMED_THUMBNAILS, "timed_thumbnails"),
+        (AVDisposition::CAPTIONS, "captions"),
+        (AVDisposition::DESCRIPTIONS, "descriptions"),
+        (AVDisposition::METADATA, "metadata"),
+        (AVDisposition::DEPENDENT, "dependent"),
+        (AVDisposition::STILL_IMAGE, "still_image"),
+    ]
+    .iter()
+    .filter(|(flag, _)| disposition.contains(*flag))
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
@@ -0,0 +1,86 @@
+use std::io::Write;
+use std::path::Path;
+
+use media_time::MediaTime;
+use serde::Serialize;
+
+use crate::report::ExtractReport;
+
+/// Receives events while media is extracted, all callbacks default to doing nothing
+pub trait ExtractObserver {
+    fn stream_selected(&mut self, _index: i32, _codec: &str) {}
+    /// `duration` is `None` if the file doesn't know its duration
+    fn progress(&mut self, _current: MediaTime, _duration: Option<MediaTime>) {}
+    fn sheet_written(&mut self, _path: &Path) {}
+    fn warning(&mut self, _message: &str) {}
+    fn finished(&mut self, _report: &ExtractReport) {}
+}
+
+pub struct NoopObserver;
+
+impl ExtractObserver for NoopObserver {}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    StreamSelected {
+        index: i32,
+        codec: &'a str,
+    },
+    Progress {
+        #[serde(with = "crate::seconds")]
+        current: MediaTime,
+        #[serde(with = "crate::seconds::option")]
+        duration: Option<MediaTime>,
+    },
+    SheetWritten {
+        path: &'a Path,
+    },
+    Warning {
+        message: &'a str,
+    },
+    Finished {
+        report: &'a ExtractReport,
+    },
+}
+
+/// Writes every event as a single line of JSON
+pub struct JsonLinesObserver<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesObserver<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesObserver { writer }
+    }
+
+    fn write(&mut self, event: Event) {
+        // Progress reporting must never abort an extraction, so write errors are dropped
+        if serde_json::to_writer(&mut self.writer, &event).is_ok() {
+            let _ = self.writer.write_all(b"\n");
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+impl<W: Write> ExtractObserver for JsonLinesObserver<W> {
+    fn stream_selected(&mut self, index: i32, codec: &str) {
+        self.write(Event::StreamSelected { index, codec })
+    }
+
+    fn progress(&mut self, current: MediaTime, duration: Option<MediaTime>) {
+        self.write(Event::Progress { current, duration })
+    }
+
+    fn sheet_written(&mut self, path: &Path) {
+        self.write(Event::SheetWritten { path })
+    }
+
+    fn warning(&mut self, message: &str) {
+        self.write(Event::Warning { message })
+    }
+
+    fn finished(&mut self, report: &ExtractReport) {
+        self.write(Event::Finished { report })
+    }
+}
@@ -0,0 +1,353 @@
+use std::time::Duration;
+
+use ffmpeg_api::enums::{SwsFlags, SwsScaler};
+use image::ImageOutputFormat;
+use media_time::MediaTime;
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::cancel::CancellationToken;
+use crate::spritesheet::ImageFormat;
+use crate::SamplingMode;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq)]
+pub enum OptionsError {
+    #[error("Frame interval has to be positive, got {0}")]
+    FrameIntervalNotPositive(MediaTime),
+    #[error("HLS segment duration has to be a positive number of whole seconds, got {0}")]
+    HlsSegmentDurationInvalid(MediaTime),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractOptions {
+    pub max_size: u32,
+    pub num_horizontal: u32,
+    pub num_vertical: u32,
+    #[serde(
+        serialize_with = "crate::seconds::serialize",
+        deserialize_with = "deserialize_frame_interval"
+    )]
+    pub frame_interval: MediaTime,
+    /// Frames before this point in time, relative to the start of the file, are not sampled
+    #[serde(with = "crate::seconds")]
+    pub start_offset: MediaTime,
+    pub format: ImageFormat,
+    pub quality: u8,
+    #[serde(with = "scaler")]
+    pub scaler: SwsScaler,
+    pub sampling: SamplingMode,
+    pub video_stream: Option<i32>,
+    pub thumbnail_max_size: Option<u32>,
+    pub waveform_samples_per_pixel: u32,
+    pub waveform_image_size: Option<(u32, u32)>,
+    /// Measures the loudness of the audio streams of video files, always done for audio files
+    pub loudness: bool,
+    /// Extracts CEA-608 closed captions, which requires decoding every video frame
+    pub captions: bool,
+    /// Splits the WebVTT tracks into HLS segments of this duration, with a media playlist
+    #[serde(
+        serialize_with = "crate::seconds::option::serialize",
+        deserialize_with = "deserialize_hls_segment_duration"
+    )]
+    pub hls_segment_duration: Option<MediaTime>,
+    /// MPEG-TS timestamp the segments' X-TIMESTAMP-MAP maps media time zero to
+    pub hls_mpegts_offset: u64,
+    pub fast_chroma: bool,
+    pub fast_rounding: bool,
+    pub fast_scaling: bool,
+    #[serde(with = "crate::seconds::duration")]
+    pub timeout: Option<Duration>,
+    #[serde(skip)]
+    pub cancellation: CancellationToken,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            max_size: 240,
+            num_horizontal: 5,
+            num_vertical: 5,
+            frame_interval: MediaTime::from_seconds(2),
+            start_offset: MediaTime::from_seconds(0),
+            format: ImageFormat::Jpeg,
+            quality: 90,
+            scaler: SwsScaler::Area,
+            sampling: SamplingMode::Sequential,
+            video_stream: None,
+            thumbnail_max_size: None,
+            waveform_samples_per_pixel: 256,
+            waveform_image_size: None,
+            loudness: false,
+            captions: false,
+            hls_segment_duration: None,
+            hls_mpegts_offset: 0,
+            fast_chroma: false,
+            fast_rounding: false,
+            fast_scaling: false,
+            timeout: None,
+            cancellation: CancellationToken::new(),
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn num_horizontal(mut self, num_horizontal: u32) -> Self {
+        self.num_horizontal = num_horizontal;
+        self
+    }
+
+    pub fn num_vertical(mut self, num_vertical: u32) -> Self {
+        self.num_vertical = num_vertical;
+        self
+    }
+
+    /// Fails for intervals that aren't positive, as sampling would never advance
+    pub fn frame_interval(mut self, frame_interval: MediaTime) -> Result<Self, OptionsError> {
+        self.frame_interval = validate_frame_interval(frame_interval)?;
+        Ok(self)
+    }
+
+    pub fn start_offset(mut self, start_offset: MediaTime) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+
+    pub fn format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn scaler(mut self, scaler: SwsScaler) -> Self {
+        self.scaler = scaler;
+        self
+    }
+
+    pub fn sampling(mut self, sampling: SamplingMode) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn video_stream(mut self, video_stream: Option<i32>) -> Self {
+        self.video_stream = video_stream;
+        self
+    }
+
+    pub fn thumbnail_max_size(mut self, thumbnail_max_size: Option<u32>) -> Self {
+        self.thumbnail_max_size = thumbnail_max_size;
+        self
+    }
+
+    pub fn waveform_samples_per_pixel(mut self, waveform_samples_per_pixel: u32) -> Self {
+        self.waveform_samples_per_pixel = waveform_samples_per_pixel;
+        self
+    }
+
+    pub fn waveform_image_size(mut self, waveform_image_size: Option<(u32, u32)>) -> Self {
+        self.waveform_image_size = waveform_image_size;
+        self
+    }
+
+    pub fn loudness(mut self, loudness: bool) -> Self {
+        self.loudness = loudness;
+        self
+    }
+
+    pub fn captions(mut self, captions: bool) -> Self {
+        self.captions = captions;
+        self
+    }
+
+    pub fn hls_segment_duration(
+        mut self,
+        hls_segment_duration: Option<MediaTime>,
+    ) -> Result<Self, OptionsError> {
+        self.hls_segment_duration = hls_segment_duration
+            .map(validate_hls_segment_duration)
+            .transpose()?;
+        Ok(self)
+    }
+
+    pub fn hls_mpegts_offset(mut self, hls_mpegts_offset: u64) -> Self {
+        self.hls_mpegts_offset = hls_mpegts_offset;
+        self
+    }
+
+    pub fn fast_chroma(mut self, fast_chroma: bool) -> Self {
+        self.fast_chroma = fast_chroma;
+        self
+    }
+
+    pub fn fast_rounding(mut self, fast_rounding: bool) -> Self {
+        self.fast_rounding = fast_rounding;
+        self
+    }
+
+    pub fn fast_scaling(mut self, fast_scaling: bool) -> Self {
+        self.fast_scaling = fast_scaling;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Checks options that can't be enforced when setting fields directly
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        validate_frame_interval(self.frame_interval)?;
+        if let Some(hls_segment_duration) = self.hls_segment_duration {
+            validate_hls_segment_duration(hls_segment_duration)?;
+        }
+        Ok(())
+    }
+
+    pub fn image_output_format(&self) -> ImageOutputFormat {
+        match self.format {
+            ImageFormat::Jpeg => ImageOutputFormat::Jpeg(self.quality),
+            ImageFormat::Png => ImageOutputFormat::Png,
+            ImageFormat::Bmp => ImageOutputFormat::Bmp,
+        }
+    }
+
+    pub fn sws_flags(&self) -> SwsFlags {
+        let mut flags = SwsFlags::empty();
+        if !self.fast_chroma {
+            flags |= SwsFlags::FULL_CHROMA_INTERPOLATION | SwsFlags::FULL_CHROMA_INPUT;
+        }
+        if !self.fast_rounding {
+            flags |= SwsFlags::ACCURATE_ROUNDING;
+        }
+        if !self.fast_scaling {
+            flags |= SwsFlags::BIT_EXACT_SCALING;
+        }
+        flags
+    }
+}
+
+fn validate_frame_interval(frame_interval: MediaTime) -> Result<MediaTime, OptionsError> {
+    if frame_interval > MediaTime::from_seconds(0) {
+        Ok(frame_interval)
+    } else {
+        Err(OptionsError::FrameIntervalNotPositive(frame_interval))
+    }
+}
+
+fn deserialize_frame_interval<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<MediaTime, D::Error> {
+    validate_frame_interval(crate::seconds::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
+
+/// EXT-X-TARGETDURATION is a whole number of seconds
+fn validate_hls_segment_duration(duration: MediaTime) -> Result<MediaTime, OptionsError> {
+    if duration > MediaTime::from_seconds(0) && duration.nanoseconds() % 1_000_000_000 == 0 {
+        Ok(duration)
+    } else {
+        Err(OptionsError::HlsSegmentDurationInvalid(duration))
+    }
+}
+
+fn deserialize_hls_segment_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<MediaTime>, D::Error> {
+    crate::seconds::option::deserialize(deserializer)?
+        .map(validate_hls_segment_duration)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+pub fn parse_scaler(src: &str) -> Result<SwsScaler, String> {
+    match src {
+        "fast_bilinear" => Ok(SwsScaler::FastBilinear),
+        "bilinear" => Ok(SwsScaler::Bilinear),
+        "bicubic" => Ok(SwsScaler::Bicubic),
+        "x" => Ok(SwsScaler::X),
+        "point" => Ok(SwsScaler::Point),
+        "area" => Ok(SwsScaler::Area),
+        "bicublin" => Ok(SwsScaler::Bicublin),
+        "gauss" => Ok(SwsScaler::Gauss),
+        "sinc" => Ok(SwsScaler::Sinc),
+        "lanczos" => Ok(SwsScaler::Lanczos),
+        "spline" => Ok(SwsScaler::Spline),
+        _ => Err(format!("Invalid scaler: {}", src)),
+    }
+}
+
+pub fn scaler_name(scaler: SwsScaler) -> &'static str {
+    match scaler {
+        SwsScaler::FastBilinear => "fast_bilinear",
+        SwsScaler::Bilinear => "bilinear",
+        SwsScaler::Bicubic => "bicubic",
+        SwsScaler::X => "x",
+        SwsScaler::Point => "point",
+        SwsScaler::Area => "area",
+        SwsScaler::Bicublin => "bicublin",
+        SwsScaler::Gauss => "gauss",
+        SwsScaler::Sinc => "sinc",
+        SwsScaler::Lanczos => "lanczos",
+        SwsScaler::Spline => "spline",
+    }
+}
+
+pub fn parse_sampling_mode(src: &str) -> Result<SamplingMode, String> {
+    match src {
+        "sequential" => Ok(SamplingMode::Sequential),
+        "seek" => Ok(SamplingMode::Seek),
+        _ => Err(format!("Invalid sampling mode: {}", src)),
+    }
+}
+
+pub fn parse_image_format(src: &str) -> Result<ImageFormat, String> {
+    match src {
+        "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+        "png" => Ok(ImageFormat::Png),
+        "bmp" => Ok(ImageFormat::Bmp),
+        _ => Err(format!("Unsupported image format: {}", src)),
+    }
+}
+
+pub fn parse_image_size(src: &str) -> Result<(u32, u32), String> {
+    let mut parts = src.splitn(2, 'x');
+    match (
+        parts.next().and_then(|width| width.parse().ok()),
+        parts.next().and_then(|height| height.parse().ok()),
+    ) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!("Invalid image size: {}", src)),
+    }
+}
+
+mod scaler {
+    use ffmpeg_api::enums::SwsScaler;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(scaler: &SwsScaler, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(super::scaler_name(*scaler))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SwsScaler, D::Error> {
+        super::parse_scaler(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
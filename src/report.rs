@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::subtitles::SubtitleTrack;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtractReport {
+    pub files: Vec<PathBuf>,
+    pub sprites: Option<SpriteGeometry>,
+    pub frames_decoded: u64,
+    pub frames_sampled: u64,
+    pub subtitles: Vec<SubtitleTrack>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteGeometry {
+    pub sprite_width: u32,
+    pub sprite_height: u32,
+    pub num_horizontal: u32,
+    pub num_vertical: u32,
+    pub spritesheets: u32,
+}
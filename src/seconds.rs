@@ -0,0 +1,58 @@
+use media_time::MediaTime;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(time: &MediaTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(time.nanoseconds() as f64 / 1e9)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MediaTime, D::Error> {
+    Ok(from_seconds(f64::deserialize(deserializer)?))
+}
+
+fn from_seconds(seconds: f64) -> MediaTime {
+    MediaTime::from_nanos((seconds * 1e9).round() as i64)
+}
+
+pub mod option {
+    use media_time::MediaTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<MediaTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<MediaTime>, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.map(super::from_seconds))
+    }
+}
+
+pub mod duration {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_f64(duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?
+            .map(|seconds| Duration::from_millis((seconds.max(0.0) * 1000.0).round() as u64)))
+    }
+}
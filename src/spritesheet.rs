@@ -6,11 +6,17 @@ use anyhow::{bail, format_err, Error};
 use image::{DynamicImage, ImageOutputFormat, RgbImage};
 
 use media_time::MediaTime;
-use webvtt::{WebVTTCue, WebVTTFile};
+use serde::{Deserialize, Serialize};
+use webvtt::{WebVTTCue, WebVTTFile, WebVTTWriter};
 
+use crate::report::SpriteGeometry;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ImageFormat {
-    Jpeg(i32),
+    Jpeg,
     Png,
+    Bmp,
 }
 
 pub struct SpritesheetManager {
@@ -23,11 +29,15 @@ pub struct SpritesheetManager {
     current_image: u32,
     last_timestamp: MediaTime,
     frame_interval: MediaTime,
-    metadata: WebVTTFile,
+    // Created on initialization, cues are written as soon as a sprite's end is known
+    metadata: Option<WebVTTWriter<BufWriter<File>>>,
+    // The written cues, one per sprite, kept for segmenting the track
+    track: WebVTTFile,
     output_path: PathBuf,
     name: String,
     format: ImageOutputFormat,
     initialized: bool,
+    files: Vec<PathBuf>,
 }
 
 impl SpritesheetManager {
@@ -50,15 +60,17 @@ impl SpritesheetManager {
             current_image: 0,
             last_timestamp: MediaTime::from_millis(0),
             frame_interval,
-            metadata: WebVTTFile::new(),
+            metadata: None,
+            track: WebVTTFile::new(),
             output_path: output_path.into(),
             name: String::from(name.as_ref()),
             format,
             initialized: false,
+            files: Vec::new(),
         }
     }
 
-    pub fn initialize(&mut self, width: u32, height: u32) {
+    pub fn initialize(&mut self, width: u32, height: u32) -> Result<(), Error> {
         if width >= height {
             self.sprite_width = self.max_side;
             self.sprite_height = self.sprite_width * height / width;
@@ -67,7 +79,13 @@ impl SpritesheetManager {
             self.sprite_width = self.sprite_height * width / height;
         }
         self.spritesheet = self.reinit_buffer();
+        self.metadata =
+            Some(WebVTTWriter::create(self.metadata_path()).map_err(|error| {
+                format_err!("Could not create spritesheet metadata: {}", error)
+            })?);
         self.initialized = true;
+
+        Ok(())
     }
 
     fn reinit_buffer(&self) -> RgbImage {
@@ -89,6 +107,33 @@ impl SpritesheetManager {
         self.sprite_height
     }
 
+    pub fn geometry(&self) -> Option<SpriteGeometry> {
+        if !self.initialized {
+            return None;
+        }
+
+        Some(SpriteGeometry {
+            sprite_width: self.sprite_width,
+            sprite_height: self.sprite_height,
+            num_horizontal: self.num_horizontal,
+            num_vertical: self.num_vertical,
+            spritesheets: self.files.len() as u32,
+        })
+    }
+
+    pub fn metadata_path(&self) -> PathBuf {
+        self.output_path.join(format!("{}.vtt", self.name))
+    }
+
+    /// Cues written to the metadata so far
+    pub fn track(&self) -> &WebVTTFile {
+        &self.track
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
     fn sprite_index(&self, current: u32) -> u32 {
         current % (self.num_horizontal * self.num_vertical)
     }
@@ -103,12 +148,7 @@ impl SpritesheetManager {
     }
 
     fn ending(&self) -> String {
-        String::from(match self.format {
-            ImageOutputFormat::Png => "png",
-            ImageOutputFormat::Jpeg(_) => "jpeg",
-            ImageOutputFormat::Bmp => "bmp",
-            _ => panic!("Invalid image format: {:?}", self.format),
-        })
+        String::from(extension(&self.format))
     }
 
     fn y(&self, current: u32) -> u32 {
@@ -136,7 +176,7 @@ impl SpritesheetManager {
         image::imageops::overlay(&mut self.spritesheet, &image, x, y);
 
         if self.current_image != 0 {
-            self.end_frame(timestamp);
+            self.end_frame(timestamp)?;
         }
 
         if self.sprite_index(self.current_image + 1) == 0 {
@@ -149,8 +189,8 @@ impl SpritesheetManager {
         Ok(())
     }
 
-    pub fn end_frame(&mut self, timestamp: MediaTime) {
-        self.metadata.add(WebVTTCue::new(
+    pub fn end_frame(&mut self, timestamp: MediaTime) -> Result<(), Error> {
+        let cue = WebVTTCue::new(
             self.last_timestamp,
             timestamp,
             format!(
@@ -163,7 +203,15 @@ impl SpritesheetManager {
                 self.sprite_width,
                 self.sprite_height
             ),
-        ));
+        );
+        if let Some(metadata) = &mut self.metadata {
+            metadata
+                .write_cue(&cue)
+                .map_err(|error| format_err!("Could not write spritesheet metadata: {}", error))?;
+        }
+        self.track.add(cue);
+
+        Ok(())
     }
 
     fn save_spritesheet(&mut self) -> Result<(), Error> {
@@ -174,22 +222,35 @@ impl SpritesheetManager {
             self.ending(),
         );
 
-        let file = File::create(self.output_path.join(&name))
+        let path = self.output_path.join(&name);
+        let file = File::create(&path)
             .map_err(|err| format_err!("Could not create spritesheet {}: {}", &name, err))?;
 
         let new_buffer = self.reinit_buffer();
         DynamicImage::ImageRgb8(std::mem::replace(&mut self.spritesheet, new_buffer))
             .write_to(&mut BufWriter::new(file), self.format.clone())
             .map_err(|err| format_err!("Could not write spritesheet {}: {}", &name, err))?;
+        self.files.push(path);
 
         Ok(())
     }
 
     pub fn save(&mut self) -> Result<(), Error> {
         self.save_spritesheet()?;
-        self.metadata
-            .save(self.output_path.join(format!("{}.vtt", self.name)))
-            .map_err(|error| format_err!("Could not write spritesheet metadata: {}", error))?;
+        if let Some(metadata) = &mut self.metadata {
+            metadata
+                .flush()
+                .map_err(|error| format_err!("Could not write spritesheet metadata: {}", error))?;
+        }
         Ok(())
     }
 }
+
+pub fn extension(format: &ImageOutputFormat) -> &'static str {
+    match format {
+        ImageOutputFormat::Png => "png",
+        ImageOutputFormat::Jpeg(_) => "jpeg",
+        ImageOutputFormat::Bmp => "bmp",
+        _ => panic!("Invalid image format: {:?}", format),
+    }
+}
@@ -0,0 +1,206 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Error};
+use ffmpeg_api::api::*;
+use ffmpeg_api::enums::{AVDisposition, AVSubtitleType};
+use fraction::Fraction;
+use media_time::MediaTime;
+use serde::{Deserialize, Serialize};
+use webvtt::{ass, escape, CueSettings, WebVTTCue, WebVTTFile};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleTrack {
+    pub index: i32,
+    pub codec: String,
+    pub path: PathBuf,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    pub cues: usize,
+    /// HLS media playlist of the segmented track
+    pub playlist: Option<PathBuf>,
+}
+
+struct Cue {
+    start: MediaTime,
+    end: Option<MediaTime>,
+    settings: CueSettings,
+    text: String,
+}
+
+/// Decodes a text subtitle stream into WebVTT cues
+pub(crate) struct SubtitleExtractor {
+    index: i32,
+    codec: String,
+    language: Option<String>,
+    title: Option<String>,
+    disposition: AVDisposition,
+    time_base: Fraction,
+    codec_context: AVCodecContext,
+    subtitle: AVSubtitle,
+    cues: Vec<Cue>,
+}
+
+impl SubtitleExtractor {
+    pub fn new(stream: &AVStream) -> Result<Self, Error> {
+        let codec_parameters = stream.codec_parameters()?;
+        let local_codec = codec_parameters.find_decoder()?;
+
+        let mut codec_context = AVCodecContext::new(&local_codec)
+            .map_err(|error| format_err!("Could not init codec context: {}", error))?;
+        codec_context.set_parameters(&codec_parameters);
+        codec_context.set_pkt_timebase(&stream.time_base());
+        codec_context
+            .open(&local_codec, None)
+            .map_err(|error| format_err!("Could not open codec: {}", error))?;
+
+        let metadata = stream.metadata();
+        Ok(SubtitleExtractor {
+            index: stream.index(),
+            codec: codec_parameters.codec_name()?,
+            language: metadata
+                .get("language")
+                .filter(|language| language != "und"),
+            title: metadata.get("title"),
+            disposition: stream.disposition(),
+            time_base: stream.time_base(),
+            codec_context,
+            subtitle: AVSubtitle::new(),
+            cues: Vec::new(),
+        })
+    }
+
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    pub fn decode(&mut self, packet: &mut AVPacket) -> Result<(), Error> {
+        if !self
+            .codec_context
+            .decode_subtitle(packet, &mut self.subtitle)
+            .map_err(|error| format_err!("Could not decode subtitle: {}", error))?
+        {
+            return Ok(());
+        }
+        // Packets without timestamp can't be placed on the timeline
+        let pts = match packet.pts() {
+            Some(pts) => pts,
+            None => return Ok(()),
+        };
+
+        let timestamp = MediaTime::from_rational(pts, &self.time_base)?;
+        let start = timestamp + MediaTime::from_millis(self.subtitle.start_display_time() as i64);
+        // Some formats only signal the end by the next event
+        let end = match self.subtitle.end_display_time() {
+            end if end > self.subtitle.start_display_time() && end != u32::MAX => {
+                Some(timestamp + MediaTime::from_millis(end as i64))
+            }
+            _ => None,
+        };
+
+        if let Some(previous) = self.cues.last_mut() {
+            if previous.end.is_none() {
+                previous.end = Some(std::cmp::max(start, previous.start));
+            }
+        }
+
+        let mut settings = CueSettings::default();
+        let text = self
+            .subtitle
+            .rects()
+            .filter_map(|rect| match rect.rect_type() {
+                AVSubtitleType::Ass => rect.ass().map(|event| {
+                    let (text, alignment) = ass::convert_text(dialogue_text(&event));
+                    if !alignment.is_empty() {
+                        settings = alignment;
+                    }
+                    text
+                }),
+                AVSubtitleType::Text => rect.text().map(|text| escape(&text)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = clean_lines(&text);
+        if !text.is_empty() {
+            self.cues.push(Cue {
+                start,
+                end,
+                settings,
+                text,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Writes the track as `<index>.<language>.vtt` and returns it along with its description,
+    /// cues without end last until `duration`
+    pub fn save(
+        self,
+        output_path: &Path,
+        duration: MediaTime,
+    ) -> Result<(SubtitleTrack, WebVTTFile), Error> {
+        let mut track = WebVTTFile::new();
+        for cue in &self.cues {
+            track.add(
+                WebVTTCue::new(
+                    cue.start,
+                    cue.end
+                        .unwrap_or_else(|| std::cmp::max(duration, cue.start)),
+                    cue.text.clone(),
+                )
+                .with_settings(cue.settings.clone()),
+            );
+        }
+
+        let path = output_path.join(match &self.language {
+            Some(language) => format!("{}.{}.vtt", self.index, language),
+            None => format!("{}.vtt", self.index),
+        });
+        track
+            .save(&path)
+            .map_err(|error| format_err!("Could not write subtitle track: {}", error))?;
+
+        let description = SubtitleTrack {
+            index: self.index,
+            codec: self.codec,
+            path,
+            language: self.language,
+            title: self.title,
+            default: self.disposition.contains(AVDisposition::DEFAULT),
+            forced: self.disposition.contains(AVDisposition::FORCED),
+            hearing_impaired: self.disposition.contains(AVDisposition::HEARING_IMPAIRED),
+            cues: self.cues.len(),
+            playlist: None,
+        };
+        Ok((description, track))
+    }
+}
+
+/// The text field of a decoded event, which is formatted as
+/// `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`,
+/// or as a full `Dialogue:` line by older decoders
+fn dialogue_text(event: &str) -> &str {
+    let (event, fields) = if event.starts_with("Dialogue:") {
+        (&event["Dialogue:".len()..], 9)
+    } else {
+        (event, 8)
+    };
+    event.splitn(fields + 1, ',').nth(fields).unwrap_or("")
+}
+
+/// Cue payloads must not contain empty lines, as those terminate the cue
+fn clean_lines(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
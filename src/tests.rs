@@ -0,0 +1,316 @@
+use std::f64::consts::PI;
+
+use media_time::MediaTime;
+
+use crate::captions::CaptionDecoder;
+use crate::loudness::LoudnessMeter;
+use crate::options::OptionsError;
+use crate::ExtractOptions;
+
+#[test]
+fn frame_interval_has_to_be_positive() {
+    for interval in &[MediaTime::from_seconds(0), MediaTime::from_millis(-500)] {
+        assert_eq!(
+            ExtractOptions::new().frame_interval(*interval),
+            Err(OptionsError::FrameIntervalNotPositive(*interval))
+        );
+    }
+    let options = ExtractOptions::new()
+        .frame_interval(MediaTime::from_millis(500))
+        .unwrap();
+    assert_eq!(options.frame_interval, MediaTime::from_millis(500));
+    assert!(options.validate().is_ok());
+
+    let options = ExtractOptions {
+        frame_interval: MediaTime::from_seconds(0),
+        ..ExtractOptions::default()
+    };
+    assert!(options.validate().is_err());
+
+    assert!(serde_json::from_str::<ExtractOptions>(r#"{"frame_interval": 0}"#).is_err());
+    assert!(serde_json::from_str::<ExtractOptions>(r#"{"frame_interval": -1.5}"#).is_err());
+    let options = serde_json::from_str::<ExtractOptions>(r#"{"frame_interval": 0.5}"#).unwrap();
+    assert_eq!(options.frame_interval, MediaTime::from_millis(500));
+}
+
+#[test]
+fn hls_segment_duration_has_to_be_whole_seconds() {
+    for duration in &[
+        MediaTime::from_seconds(0),
+        MediaTime::from_seconds(-4),
+        MediaTime::from_millis(2500),
+    ] {
+        assert_eq!(
+            ExtractOptions::new().hls_segment_duration(Some(*duration)),
+            Err(OptionsError::HlsSegmentDurationInvalid(*duration))
+        );
+    }
+    let options = ExtractOptions::new()
+        .hls_segment_duration(Some(MediaTime::from_seconds(6)))
+        .unwrap();
+    assert_eq!(
+        options.hls_segment_duration,
+        Some(MediaTime::from_seconds(6))
+    );
+    assert!(ExtractOptions::new()
+        .hls_segment_duration(None)
+        .unwrap()
+        .validate()
+        .is_ok());
+
+    let options = ExtractOptions {
+        hls_segment_duration: Some(MediaTime::from_seconds(0)),
+        ..ExtractOptions::default()
+    };
+    assert!(options.validate().is_err());
+
+    assert!(serde_json::from_str::<ExtractOptions>(r#"{"hls_segment_duration": 0}"#).is_err());
+    assert!(serde_json::from_str::<ExtractOptions>(r#"{"hls_segment_duration": 1.5}"#).is_err());
+    let options =
+        serde_json::from_str::<ExtractOptions>(r#"{"hls_segment_duration": null}"#).unwrap();
+    assert_eq!(options.hls_segment_duration, None);
+    let options = serde_json::from_str::<ExtractOptions>(r#"{"hls_segment_duration": 6}"#).unwrap();
+    assert_eq!(
+        options.hls_segment_duration,
+        Some(MediaTime::from_seconds(6))
+    );
+}
+
+#[test]
+fn seconds_keep_nanosecond_precision() {
+    let options = ExtractOptions {
+        start_offset: MediaTime::from_nanos(1_500_000_001),
+        ..ExtractOptions::default()
+    };
+    let json = serde_json::to_value(&options).unwrap();
+    assert_eq!(json["start_offset"], serde_json::json!(1.500000001));
+
+    let options =
+        serde_json::from_str::<ExtractOptions>(r#"{"start_offset": 0.000000250}"#).unwrap();
+    assert_eq!(options.start_offset, MediaTime::from_nanos(250));
+}
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// Interleaved stereo sine with the same signal on both channels
+fn stereo_sine(frequency: f64, dbfs: f64, phase: f64, seconds: f64) -> Vec<f32> {
+    let amplitude = 10f64.powf(dbfs / 20.0);
+    let frames = (seconds * SAMPLE_RATE as f64) as usize;
+    (0..frames)
+        .flat_map(|n| {
+            let t = n as f64 / SAMPLE_RATE as f64;
+            let sample = (amplitude * (2.0 * PI * frequency * t + phase).sin()) as f32;
+            vec![sample, sample]
+        })
+        .collect()
+}
+
+fn measure(samples: &[f32]) -> crate::loudness::Loudness {
+    let mut meter = LoudnessMeter::new(SAMPLE_RATE, vec![1.0, 1.0]);
+    meter.add_samples(samples);
+    meter.finish()
+}
+
+#[test]
+fn loudness_of_sine_matches_reference() {
+    // EBU Tech 3341 reference: a stereo 997 Hz sine at -23 dBFS reads -23 LUFS
+    let loudness = measure(&stereo_sine(997.0, -23.0, 0.0, 10.0));
+    let integrated = loudness.integrated.unwrap();
+    assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+    assert!(loudness.range.unwrap() < 0.1);
+    assert!((loudness.true_peak_db().unwrap() + 23.0).abs() < 0.1);
+
+    let loudness = measure(&stereo_sine(997.0, -20.0, 0.0, 10.0));
+    let integrated = loudness.integrated.unwrap();
+    assert!((integrated + 20.0).abs() < 0.1, "{}", integrated);
+    assert!((loudness.replaygain_gain().unwrap() - 2.0).abs() < 0.1);
+}
+
+#[test]
+fn silence_has_no_loudness() {
+    let loudness = measure(&vec![0.0; SAMPLE_RATE as usize * 2 * 5]);
+    assert_eq!(loudness.integrated, None);
+    assert_eq!(loudness.range, None);
+    assert_eq!(loudness.true_peak, 0.0);
+    assert_eq!(loudness.true_peak_db(), None);
+}
+
+#[test]
+fn true_peak_finds_inter_sample_peaks() {
+    // Every sample of this sine is at 45° from its peaks
+    let samples = stereo_sine(SAMPLE_RATE as f64 / 4.0, -6.0, PI / 4.0, 1.0);
+    let sample_peak = samples
+        .iter()
+        .fold(0.0, |peak: f64, sample| peak.max(sample.abs() as f64));
+    let loudness = measure(&samples);
+    assert!(
+        loudness.true_peak > sample_peak * 1.2,
+        "{} {}",
+        loudness.true_peak,
+        sample_peak
+    );
+    assert!((loudness.true_peak_db().unwrap() + 6.0).abs() < 0.5);
+}
+
+#[test]
+fn gating_drops_quiet_passages() {
+    let mut samples = stereo_sine(997.0, -23.0, 0.0, 10.0);
+    // Above the absolute gate, but more than 10 LU below the loud part
+    samples.extend(stereo_sine(997.0, -50.0, 0.0, 10.0));
+    let integrated = measure(&samples).integrated.unwrap();
+    assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+}
+
+const RCL: (u8, u8) = (0x14, 0x20);
+const BS: (u8, u8) = (0x14, 0x21);
+const RU2: (u8, u8) = (0x14, 0x25);
+const EDM: (u8, u8) = (0x14, 0x2c);
+const CR: (u8, u8) = (0x14, 0x2d);
+const EOC: (u8, u8) = (0x14, 0x2f);
+/// Preamble address code for row 15, column 0
+const PAC_ROW_15: (u8, u8) = (0x14, 0x70);
+
+fn with_parity(byte: u8) -> u8 {
+    if byte.count_ones() % 2 == 0 {
+        byte | 0x80
+    } else {
+        byte
+    }
+}
+
+/// Sends each pair as valid field 1 `cc_data` of its own frame
+fn feed(decoder: &mut CaptionDecoder, pairs: &[(u8, u8)], millis: i64) {
+    for (first, second) in pairs {
+        decoder.decode(
+            &[0xfc, with_parity(*first), with_parity(*second)],
+            MediaTime::from_millis(millis),
+        );
+    }
+}
+
+fn text(text: &str) -> Vec<(u8, u8)> {
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| (pair[0], pair.get(1).copied().unwrap_or(0)))
+        .collect()
+}
+
+fn captions(decoder: CaptionDecoder, name: &str, end: i64) -> Vec<(i128, i128, String)> {
+    let directory =
+        std::env::temp_dir().join(format!("media-ingestion-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&directory).unwrap();
+    let saved = decoder
+        .save(&directory, "captions", MediaTime::from_millis(end))
+        .unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    match saved {
+        Some((_, track)) => track
+            .cues()
+            .map(|cue| {
+                (
+                    cue.start().milliseconds(),
+                    cue.end().milliseconds(),
+                    cue.payload().to_string(),
+                )
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[test]
+fn pop_on_captions_show_from_end_of_caption_until_erased() {
+    let mut decoder = CaptionDecoder::new();
+    feed(&mut decoder, &[RCL, RCL, PAC_ROW_15, PAC_ROW_15], 0);
+    feed(&mut decoder, &text("Hello"), 100);
+    // Loaded captions aren't shown before the end of caption command
+    feed(&mut decoder, &[EOC, EOC], 1000);
+    feed(&mut decoder, &[RCL, RCL, PAC_ROW_15, PAC_ROW_15], 1500);
+    feed(&mut decoder, &text("World"), 1600);
+    feed(&mut decoder, &[EOC, EOC], 2000);
+    feed(&mut decoder, &[EDM, EDM], 3000);
+
+    assert_eq!(
+        captions(decoder, "pop-on", 10_000),
+        [
+            (1000, 2000, "Hello".to_string()),
+            (2000, 3000, "World".to_string())
+        ]
+    );
+}
+
+#[test]
+fn doubled_control_codes_are_applied_once() {
+    let mut decoder = CaptionDecoder::new();
+    feed(&mut decoder, &[RCL, RCL, PAC_ROW_15, PAC_ROW_15], 0);
+    feed(&mut decoder, &text("ABCD"), 0);
+    feed(&mut decoder, &[BS, BS], 0);
+    // A repetition that isn't directly after the first code is a new command
+    feed(&mut decoder, &[BS, BS, BS], 0);
+    feed(&mut decoder, &[EOC, EOC], 1000);
+
+    assert_eq!(
+        captions(decoder, "doubled", 2000),
+        [(1000, 2000, "A".to_string())]
+    );
+}
+
+#[test]
+fn roll_up_captions_scroll_on_carriage_return() {
+    let mut decoder = CaptionDecoder::new();
+    feed(&mut decoder, &[RU2, RU2, PAC_ROW_15, PAC_ROW_15], 0);
+    feed(&mut decoder, &text("ONE"), 500);
+    feed(&mut decoder, &[CR, CR], 1000);
+    feed(&mut decoder, &text("TWO"), 1500);
+    feed(&mut decoder, &[CR, CR], 2000);
+    feed(&mut decoder, &text("THREE"), 2500);
+    feed(&mut decoder, &[CR, CR], 3000);
+
+    assert_eq!(
+        captions(decoder, "roll-up", 4000),
+        [
+            (1000, 2000, "ONE".to_string()),
+            (2000, 3000, "ONE\nTWO".to_string()),
+            (3000, 4000, "TWO\nTHREE".to_string())
+        ]
+    );
+}
+
+#[test]
+fn extended_characters_replace_their_fallback() {
+    let mut decoder = CaptionDecoder::new();
+    feed(&mut decoder, &[RCL, RCL, PAC_ROW_15, PAC_ROW_15], 0);
+    feed(&mut decoder, &text("CAFE"), 0);
+    // É from the Spanish and French set
+    feed(&mut decoder, &[(0x12, 0x21), (0x12, 0x21)], 0);
+    feed(&mut decoder, &[EOC, EOC], 1000);
+
+    assert_eq!(
+        captions(decoder, "extended", 2000),
+        [(1000, 2000, "CAFÉ".to_string())]
+    );
+}
+
+#[test]
+fn bad_parity_and_other_channels_are_ignored() {
+    let mut decoder = CaptionDecoder::new();
+    feed(&mut decoder, &[RCL, RCL, PAC_ROW_15, PAC_ROW_15], 0);
+    feed(&mut decoder, &text("A"), 0);
+    // A single byte with even parity drops the pair
+    decoder.decode(
+        &[0xfc, with_parity(b'X'), with_parity(b'Y') ^ 0x80],
+        MediaTime::from_millis(0),
+    );
+    // Field 2 data and invalid constructs
+    decoder.decode(&[0xfd, with_parity(b'X'), 0x80], MediaTime::from_millis(0));
+    decoder.decode(&[0xf8, with_parity(b'X'), 0x80], MediaTime::from_millis(0));
+    // Text following a CC2 control code belongs to CC2
+    feed(&mut decoder, &[(0x1c, 0x20), (0x1c, 0x20)], 0);
+    feed(&mut decoder, &text("XY"), 0);
+    feed(&mut decoder, &[EOC, EOC], 1000);
+
+    assert_eq!(
+        captions(decoder, "parity", 2000),
+        [(1000, 2000, "A".to_string())]
+    );
+}
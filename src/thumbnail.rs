@@ -0,0 +1,260 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Error};
+use ffmpeg_api::api::{AVFrame, SwsContext};
+use ffmpeg_api::enums::{AVPixelFormat, SwsFlags, SwsScaler};
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use media_time::MediaTime;
+
+use crate::spritesheet;
+
+// Mean luma outside of these levels means the frame is (nearly) black or white
+const BLACK_LEVEL: f64 = 24.0;
+const WHITE_LEVEL: f64 = 232.0;
+// Frames below these are considered near-uniform or too low in contrast to be useful
+const MIN_DEVIATION: f64 = 12.0;
+const MIN_SPREAD: u32 = 48;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameScore {
+    pub rejected: bool,
+    pub value: f64,
+}
+
+impl FrameScore {
+    /// Scores a frame by its luma histogram spread and sharpness (variance of the Laplacian)
+    pub fn of(image: &RgbImage) -> FrameScore {
+        let (width, height) = image.dimensions();
+        let luma: Vec<u8> = image
+            .pixels()
+            .map(|pixel| {
+                ((299 * pixel[0] as u32 + 587 * pixel[1] as u32 + 114 * pixel[2] as u32) / 1000)
+                    as u8
+            })
+            .collect();
+        if luma.is_empty() {
+            return FrameScore {
+                rejected: true,
+                value: 0.0,
+            };
+        }
+
+        let mut histogram = [0u32; 256];
+        for &value in &luma {
+            histogram[value as usize] += 1;
+        }
+
+        let count = luma.len() as f64;
+        let mean = luma.iter().map(|&value| value as f64).sum::<f64>() / count;
+        let deviation = (luma
+            .iter()
+            .map(|&value| (value as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt();
+        let spread =
+            percentile(&histogram, luma.len(), 0.95) - percentile(&histogram, luma.len(), 0.05);
+        let sharpness = laplacian_variance(&luma, width as usize, height as usize);
+
+        FrameScore {
+            rejected: mean < BLACK_LEVEL
+                || mean > WHITE_LEVEL
+                || deviation < MIN_DEVIATION
+                || spread < MIN_SPREAD,
+            value: spread as f64 / 255.0 * sharpness.sqrt(),
+        }
+    }
+}
+
+impl PartialOrd for FrameScore {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match other.rejected.cmp(&self.rejected) {
+            Ordering::Equal => self.value.partial_cmp(&other.value),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+fn percentile(histogram: &[u32; 256], count: usize, percentile: f64) -> u32 {
+    let target = (count as f64 * percentile) as u64;
+    let mut seen = 0u64;
+    for (value, &amount) in histogram.iter().enumerate() {
+        seen += amount as u64;
+        if seen > target {
+            return value as u32;
+        }
+    }
+    255
+}
+
+fn laplacian_variance(luma: &[u8], width: usize, height: usize) -> f64 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let mut sum = 0.0;
+    let mut sum_squared = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let index = y * width + x;
+            let laplacian = 4.0 * luma[index] as f64
+                - luma[index - width] as f64
+                - luma[index + width] as f64
+                - luma[index - 1] as f64
+                - luma[index + 1] as f64;
+            sum += laplacian;
+            sum_squared += laplacian * laplacian;
+        }
+    }
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_squared / count - mean * mean
+}
+
+struct Candidate {
+    in_window: bool,
+    score: FrameScore,
+    timestamp: MediaTime,
+    image: RgbImage,
+}
+
+pub struct ThumbnailSelector {
+    max_size: Option<u32>,
+    /// Without a known duration, every frame is in the window
+    window: Option<(MediaTime, MediaTime)>,
+    output_frame: AVFrame,
+    scale_context: SwsContext,
+    scaler: SwsScaler,
+    flags: SwsFlags,
+    best: Option<Candidate>,
+}
+
+impl ThumbnailSelector {
+    /// Candidates are taken from the middle of the video, skipping intros and credits
+    pub fn new(
+        max_size: Option<u32>,
+        duration: Option<MediaTime>,
+        scaler: SwsScaler,
+        flags: SwsFlags,
+    ) -> Result<ThumbnailSelector, Error> {
+        let window = duration.map(|duration| {
+            let duration = duration.nanoseconds() as i64;
+            (
+                MediaTime::from_nanos(duration / 5),
+                MediaTime::from_nanos(duration / 5 * 4),
+            )
+        });
+        Ok(ThumbnailSelector {
+            max_size,
+            window,
+            output_frame: AVFrame::new()
+                .map_err(|error| format_err!("Could not create thumbnail frame: {}", error))?,
+            scale_context: SwsContext::new(),
+            scaler,
+            flags,
+            best: None,
+        })
+    }
+
+    fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.max_size {
+            Some(max_size) if width.max(height) > max_size => {
+                if width >= height {
+                    (max_size, max_size * height / width)
+                } else {
+                    (max_size * width / height, max_size)
+                }
+            }
+            _ => (width, height),
+        }
+    }
+
+    fn is_better(&self, in_window: bool, score: FrameScore) -> bool {
+        match &self.best {
+            None => true,
+            Some(best) => match in_window.cmp(&best.in_window) {
+                Ordering::Equal => score > best.score,
+                ordering => ordering == Ordering::Greater,
+            },
+        }
+    }
+
+    /// Offers a decoded frame as candidate, `preview` is a downscaled copy used for scoring
+    pub fn offer(
+        &mut self,
+        frame: &AVFrame,
+        timestamp: MediaTime,
+        preview: &RgbImage,
+    ) -> Result<(), Error> {
+        let in_window = self
+            .window
+            .is_none_or(|(start, end)| timestamp >= start && timestamp <= end);
+        let score = FrameScore::of(preview);
+        if !self.is_better(in_window, score) {
+            return Ok(());
+        }
+
+        let image = self.scale(frame)?;
+        self.best = Some(Candidate {
+            in_window,
+            score,
+            timestamp,
+            image,
+        });
+
+        Ok(())
+    }
+
+    fn scale(&mut self, frame: &AVFrame) -> Result<RgbImage, Error> {
+        let (width, height) = self.size(frame.width() as u32, frame.height() as u32);
+        if self.output_frame.width() != width as i32 || self.output_frame.height() != height as i32
+        {
+            self.output_frame
+                .init(width as i32, height as i32, AVPixelFormat::RGB24)
+                .map_err(|error| format_err!("Could not init thumbnail frame: {}", error))?;
+        }
+        self.scale_context
+            .reinit(frame, &self.output_frame, self.scaler, self.flags)
+            .map_err(|error| format_err!("Could not reinit thumbnail scale context: {}", error))?;
+        self.scale_context.scale(frame, &mut self.output_frame);
+
+        RgbImage::from_raw(width, height, self.output_frame.data(0).to_vec())
+            .ok_or_else(|| format_err!("Could not process thumbnail frame"))
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.best.as_ref().map_or(false, |best| best.score.rejected)
+    }
+
+    pub fn timestamp(&self) -> Option<MediaTime> {
+        self.best.as_ref().map(|best| best.timestamp)
+    }
+
+    pub fn save(
+        &self,
+        output_path: &Path,
+        name: &str,
+        format: ImageOutputFormat,
+    ) -> Result<Option<PathBuf>, Error> {
+        let best = match &self.best {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+
+        let path = output_path.join(format!("{}.{}", name, spritesheet::extension(&format)));
+        let file = File::create(&path).map_err(|error| {
+            format_err!("Could not create thumbnail {}: {}", path.display(), error)
+        })?;
+        DynamicImage::ImageRgb8(best.image.clone())
+            .write_to(&mut BufWriter::new(file), format)
+            .map_err(|error| {
+                format_err!("Could not write thumbnail {}: {}", path.display(), error)
+            })?;
+
+        Ok(Some(path))
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{format_err, Error};
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+/// Version of the BBC audiowaveform data format that is written
+pub const WAVEFORM_VERSION: i32 = 2;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const FOREGROUND: Rgb<u8> = Rgb([49, 99, 176]);
+
+/// Min/max peak pairs of a mono mixdown, compatible with BBC audiowaveform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waveform {
+    pub version: i32,
+    pub channels: i32,
+    pub sample_rate: i32,
+    pub samples_per_pixel: i32,
+    pub bits: i32,
+    pub length: u32,
+    pub data: Vec<i16>,
+}
+
+pub struct WaveformBuilder {
+    sample_rate: i32,
+    samples_per_pixel: u32,
+    data: Vec<i16>,
+    min: f32,
+    max: f32,
+    count: u32,
+}
+
+impl WaveformBuilder {
+    pub fn new(sample_rate: i32, samples_per_pixel: u32) -> Self {
+        WaveformBuilder {
+            sample_rate,
+            samples_per_pixel: samples_per_pixel.max(1),
+            data: Vec::new(),
+            min: 0.0,
+            max: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.add_sample(sample);
+        }
+    }
+
+    /// Adds interleaved samples, mixed down to mono by averaging the channels
+    pub fn add_interleaved(&mut self, samples: &[f32], channels: usize) {
+        let channels = channels.max(1);
+        for frame in samples.chunks_exact(channels) {
+            self.add_sample(frame.iter().sum::<f32>() / channels as f32);
+        }
+    }
+
+    fn add_sample(&mut self, sample: f32) {
+        if self.count == 0 {
+            self.min = sample;
+            self.max = sample;
+        } else {
+            self.min = self.min.min(sample);
+            self.max = self.max.max(sample);
+        }
+        self.count += 1;
+
+        if self.count == self.samples_per_pixel {
+            self.push_point();
+        }
+    }
+
+    fn push_point(&mut self) {
+        self.data.push(to_i16(self.min));
+        self.data.push(to_i16(self.max));
+        self.count = 0;
+    }
+
+    pub fn finish(mut self) -> Waveform {
+        if self.count > 0 {
+            self.push_point();
+        }
+
+        Waveform {
+            version: WAVEFORM_VERSION,
+            channels: 1,
+            sample_rate: self.sample_rate,
+            samples_per_pixel: self.samples_per_pixel as i32,
+            bits: 16,
+            length: (self.data.len() / 2) as u32,
+            data: self.data,
+        }
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.max(-1.0).min(1.0) * i16::MAX as f32).round() as i16
+}
+
+impl Waveform {
+    /// Writes the binary `.dat` format: a little endian header followed by 16 bit min/max pairs
+    pub fn save_dat(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let file = File::create(path.as_ref())
+            .map_err(|error| format_err!("Could not create waveform data: {}", error))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&self.version.to_le_bytes());
+        // Flags, bit 0 unset means 16 bit samples
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&self.samples_per_pixel.to_le_bytes());
+        header.extend_from_slice(&self.length.to_le_bytes());
+        header.extend_from_slice(&self.channels.to_le_bytes());
+
+        let mut body = Vec::with_capacity(self.data.len() * 2);
+        for value in &self.data {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+
+        writer
+            .write_all(&header)
+            .and_then(|_| writer.write_all(&body))
+            .and_then(|_| writer.flush())
+            .map_err(|error| format_err!("Could not write waveform data: {}", error))?;
+
+        Ok(())
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let file = File::create(path.as_ref())
+            .map_err(|error| format_err!("Could not create waveform data: {}", error))?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|error| format_err!("Could not write waveform data: {}", error))?;
+        Ok(())
+    }
+
+    pub fn render(&self, width: u32, height: u32) -> RgbImage {
+        let mut image = RgbImage::from_pixel(width, height, BACKGROUND);
+        let points = self.length as usize;
+        if points == 0 || width == 0 || height == 0 {
+            return image;
+        }
+
+        let center = (height - 1) as f32 / 2.0;
+        let y = |value: i16| (center - value as f32 / i16::MAX as f32 * center).round() as u32;
+
+        for x in 0..width {
+            let start = x as usize * points / width as usize;
+            let end = ((x as usize + 1) * points / width as usize)
+                .max(start + 1)
+                .min(points);
+
+            let (min, max) = self.data[start * 2..end * 2]
+                .chunks(2)
+                .fold((i16::MAX, i16::MIN), |(min, max), pair| {
+                    (min.min(pair[0]), max.max(pair[1]))
+                });
+
+            for row in y(max)..=y(min) {
+                image.put_pixel(x, row.min(height - 1), FOREGROUND);
+            }
+        }
+
+        image
+    }
+}